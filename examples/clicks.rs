@@ -0,0 +1,85 @@
+//! How many clicks a second the engine makes on one core, with and without a history.
+//!
+//! ```bash
+//! cargo run --release --example clicks
+//! ```
+//!
+//! Every game clicks the safe squares of an expert board in a random order until it is won, like
+//! a solver would. Squares already opened by a flood fill are turned down and not counted. The
+//! boards and click orders are made before the clock starts, so only the clicks are timed.
+//! Clicks without a history are also checked to make no allocations.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use minesweeper::game::{Difficulty, GameStatus, Minesweeper};
+use rand::{rngs::SmallRng, seq::SliceRandom, SeedableRng};
+
+const GAMES: u64 = 20_000;
+
+/// The system allocator, counting how often it allocates
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn main() {
+    let (rows, cols, mines) = Difficulty::Expert.dimensions();
+    let mut rng = SmallRng::seed_from_u64(0);
+
+    for keep_history in [false, true] {
+        let games = (0..GAMES)
+            .map(|seed| {
+                let game = Minesweeper::new_seeded(rows, cols, mines, seed)
+                    .expect("Expert boards fit their mines")
+                    .with_history(keep_history);
+
+                let mut safe = (0..rows)
+                    .flat_map(|row| (0..cols).map(move |col| (row, col)))
+                    .filter(|&(row, col)| !game.is_mine(row, col))
+                    .collect::<Vec<_>>();
+                safe.shuffle(&mut rng);
+                (game, safe)
+            })
+            .collect::<Vec<_>>();
+
+        let mut clicks = 0;
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let started = Instant::now();
+        for (mut game, safe) in games {
+            for (row, col) in safe {
+                if game.click(row, col).is_ok() {
+                    clicks += 1;
+                }
+            }
+            assert_eq!(game.status(), GameStatus::Won);
+        }
+        let seconds = started.elapsed().as_secs_f64();
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+        if !keep_history {
+            assert_eq!(allocations, 0, "Clicks without a history allocated");
+        }
+
+        println!(
+            "History {}: {clicks} clicks in {seconds:.2}s, {:.1} million a second",
+            if keep_history { "on" } else { "off" },
+            clicks as f64 / seconds / 1e6,
+        );
+    }
+}
@@ -1,8 +1,8 @@
 use burn::{data::dataloader::batcher::Batcher, prelude::*};
 
 use crate::{
-    constants::{COLS, ROWS},
-    game::Minesweeper,
+    game::{Minesweeper, MinesweeperError},
+    game3d::Minesweeper3d,
 };
 
 #[derive(Clone, Default)]
@@ -15,140 +15,187 @@ pub struct MinesweeperBatch<B: Backend> {
 }
 
 impl<B: Backend> Batcher<Minesweeper, MinesweeperBatch<B>> for MinesweeperBatcher {
+    /// Panics on an empty batch, see [`MinesweeperBatcher::try_batch`]
     fn batch(&self, items: Vec<Minesweeper>) -> MinesweeperBatch<B> {
+        self.try_batch(items)
+            .expect("The data loader never makes empty batches")
+    }
+}
+
+impl MinesweeperBatcher {
+    /// Boards smaller than the largest one in the batch are padded with closed squares
+    /// without mines, so different board sizes can be trained on together.
+    pub fn try_batch<B: Backend>(
+        &self,
+        items: Vec<Minesweeper>,
+    ) -> Result<MinesweeperBatch<B>, MinesweeperError> {
+        if items.is_empty() {
+            return Err(MinesweeperError::EmptyBatch);
+        }
+
+        let device = &B::Device::default();
+        let mut input_boards = Vec::new();
+        let mut mines = Vec::new();
+        let items_len = items.len();
+
+        let rows = items.iter().map(Minesweeper::rows).max().unwrap_or(0);
+        let cols = items.iter().map(Minesweeper::cols).max().unwrap_or(0);
+
+        for item in items {
+            let mut board_opened = vec![0.; rows * cols];
+            let mut board_vals_masked = vec![0.; rows * cols];
+            let mut mines_on_board = vec![0.; rows * cols];
+
+            for row_i in 0..item.rows() {
+                for col_i in 0..item.cols() {
+                    if !item.is_square_open(row_i, col_i) {
+                        continue;
+                    }
+
+                    use crate::game::Square::*;
+                    board_opened[row_i * cols + col_i] = 1.;
+                    board_vals_masked[row_i * cols + col_i] = match item.square_state(row_i, col_i)
+                    {
+                        Some(Nearby(v)) => v as f32,
+                        Some(Mine) => -1.,
+                        Some(Empty) | None => 0.,
+                    };
+                }
+            }
+
+            for row_i in 0..item.rows() {
+                for col_i in 0..item.cols() {
+                    let has_opened_square_around = item.is_square_open(row_i, col_i)
+                        || item
+                            .neighbours(row_i, col_i)
+                            .any(|(n_row, n_col)| item.is_square_open(n_row, n_col));
+
+                    if item.is_mine(row_i, col_i) && has_opened_square_around {
+                        mines_on_board[row_i * cols + col_i] = 1.0;
+                    }
+                }
+            }
+
+            board_opened.extend(board_vals_masked);
+            input_boards.push(
+                Tensor::<_, 3>::from_data(TensorData::new(board_opened, [2, rows, cols]), device)
+                    .unsqueeze::<4>(),
+            );
+
+            mines.push(
+                Tensor::<_, 2>::from_data(TensorData::new(mines_on_board, [rows, cols]), device)
+                    .unsqueeze::<3>(),
+            );
+        }
+
+        // let boards = Tensor::<_, 4>::cat(input_boards, 0).to_device(device);
+        // let mines = Tensor::<_, 4>::cat(mines, 0).to_device(device);
+        let boards = Tensor::<_, 4>::cat(input_boards, 0);
+        let mines = Tensor::<_, 3>::cat(mines, 0);
+
+        assert_eq!(boards.dims(), [items_len, 2, rows, cols]);
+        assert_eq!(mines.dims(), [items_len, rows, cols]);
+
+        Ok(MinesweeperBatch { boards, mines })
+    }
+}
+
+/// Encodes 3D boards the same way as [`MinesweeperBatcher`], with the layers as depth for a
+/// 3D convolution: `boards` is `[batch, 2, layers, rows, cols]`.
+#[derive(Clone, Default)]
+pub struct MinesweeperBatcher3d {}
+
+#[derive(Clone, Debug)]
+pub struct MinesweeperBatch3d<B: Backend> {
+    pub boards: Tensor<B, 5>,
+    pub mines: Tensor<B, 4>,
+}
+
+impl<B: Backend> Batcher<Minesweeper3d, MinesweeperBatch3d<B>> for MinesweeperBatcher3d {
+    /// Panics on an empty batch, see [`MinesweeperBatcher3d::try_batch`]
+    fn batch(&self, items: Vec<Minesweeper3d>) -> MinesweeperBatch3d<B> {
+        self.try_batch(items)
+            .expect("The data loader never makes empty batches")
+    }
+}
+
+impl MinesweeperBatcher3d {
+    /// Boards smaller than the largest one in the batch are padded with closed squares
+    /// without mines, so different board sizes can be trained on together.
+    pub fn try_batch<B: Backend>(
+        &self,
+        items: Vec<Minesweeper3d>,
+    ) -> Result<MinesweeperBatch3d<B>, MinesweeperError> {
+        if items.is_empty() {
+            return Err(MinesweeperError::EmptyBatch);
+        }
+
         let device = &B::Device::default();
         let mut input_boards = Vec::new();
         let mut mines = Vec::new();
         let items_len = items.len();
 
+        let layers = items.iter().map(Minesweeper3d::layers).max().unwrap_or(0);
+        let rows = items.iter().map(Minesweeper3d::rows).max().unwrap_or(0);
+        let cols = items.iter().map(Minesweeper3d::cols).max().unwrap_or(0);
+        let index = |layer: usize, row: usize, col: usize| (layer * rows + row) * cols + col;
+
         for item in items {
-            let board_opened = item
-                .opened
-                .map(|row| row.map(|val| if val { 1. } else { 0. }));
-
-            let board_vals_masked = item
-                .grid
-                .iter()
-                .zip(board_opened)
-                .map(|(grid_row, opened_row)| {
-                    grid_row
-                        .iter()
-                        .zip(opened_row)
-                        .map(|(grid_val, opened_val)| {
-                            use crate::game::Square::*;
-                            let grid_val_f = match grid_val {
-                                Empty => 0.,
-                                Nearby(v) => *v as f32,
-                                Mine => -1.,
+            let mut board_opened = vec![0.; layers * rows * cols];
+            let mut board_vals_masked = vec![0.; layers * rows * cols];
+            let mut mines_on_board = vec![0.; layers * rows * cols];
+
+            for layer_i in 0..item.layers() {
+                for row_i in 0..item.rows() {
+                    for col_i in 0..item.cols() {
+                        use crate::game::Square::*;
+                        let i = index(layer_i, row_i, col_i);
+
+                        if item.is_square_open(layer_i, row_i, col_i) {
+                            board_opened[i] = 1.;
+                            board_vals_masked[i] = match item.square_state(layer_i, row_i, col_i) {
+                                Some(Nearby(v)) => v as f32,
+                                Some(Mine) => -1.,
+                                Some(Empty) | None => 0.,
                             };
-                            // assert!(!(opened_val == 1. && grid_val_f == -1.));
-                            grid_val_f * opened_val
-                        })
-                        .collect::<Vec<_>>()
-                        .try_into()
-                        .unwrap()
-                })
-                .collect::<Vec<_>>();
+                        }
+
+                        let has_opened_square_around = item.is_square_open(layer_i, row_i, col_i)
+                            || item
+                                .neighbours(layer_i, row_i, col_i)
+                                .any(|(l, r, c)| item.is_square_open(l, r, c));
 
+                        if item.is_mine(layer_i, row_i, col_i) && has_opened_square_around {
+                            mines_on_board[i] = 1.0;
+                        }
+                    }
+                }
+            }
+
+            board_opened.extend(board_vals_masked);
             input_boards.push(
+                Tensor::<_, 4>::from_data(
+                    TensorData::new(board_opened, [2, layers, rows, cols]),
+                    device,
+                )
+                .unsqueeze::<5>(),
+            );
+
+            mines.push(
                 Tensor::<_, 3>::from_data(
-                    [board_opened, board_vals_masked.try_into().unwrap()],
+                    TensorData::new(mines_on_board, [layers, rows, cols]),
                     device,
                 )
                 .unsqueeze::<4>(),
             );
-            // mines.push(
-            //     Tensor::<_, 2>::from_data(
-            //         item.grid.map(|row| {
-            //             row.map(|val| {
-            //                 if matches!(val, crate::game::Square::Mine) {
-            //                     1.0
-            //                 } else {
-            //                     0.0
-            //                 }
-            //             })
-            //         }),
-            //         device,
-            //     )
-            //     .unsqueeze::<3>(),
-            // );
-
-            let mines_on_board: [[f32; COLS]; ROWS] = item
-                .grid
-                .iter()
-                .enumerate()
-                .map(|(row_i, row)| {
-                    row.iter()
-                        .enumerate()
-                        .map(|(col_i, val)| {
-                            use crate::game::Square::*;
-                            let has_opened_square_around = (-1..=1).any(|row_delta| {
-                                (-1..=1).any(|col_delta| {
-                                    let new_row = row_i as i32 + row_delta;
-                                    let new_col = col_i as i32 + col_delta;
-
-                                    if !(0..COLS as i32).contains(&new_col)
-                                        || !(0..ROWS as i32).contains(&new_row)
-                                    {
-                                        return false;
-                                    }
-
-                                    board_opened
-                                        .get(new_row as usize)
-                                        .and_then(|opened_row| opened_row.get(new_col as usize))
-                                        .is_some_and(|&opened_val| opened_val == 1.0)
-                                })
-                            });
-
-                            if matches!(val, Mine) && has_opened_square_around {
-                                1.0
-                            } else {
-                                0.0
-                            }
-                        })
-                        .collect::<Vec<f32>>()
-                        .try_into()
-                        .unwrap()
-                })
-                .collect::<Vec<_>>()
-                .try_into()
-                .unwrap();
-
-            mines.push(Tensor::<_, 2>::from_data(mines_on_board, device).unsqueeze::<3>());
-
-            // Could mask out all mines that can not be possibly known
-            // let mut mines = Vec::new();
-            // for row_i in 0..ROWS {
-            //     let mut mine_row = Vec::new();
-            //     for col_i in 0..COLS {
-            //         let val = item.grid[row_i][col_i];
-            //         use crate::game::Square::*;
-            //         if !matches!(val, Mine) {
-            //             mine_row.push(0);
-            //             continue;
-            //         }
-            //
-            //         for row_delta in -1..=1 {
-            //             for col_delta in -1..=1 {
-            //                 if row_delta == 0 && col_delta == 0 {
-            //                     continue;
-            //                 }
-            //
-            //                 if !(0..ROWS as i32).contains
-            //             }
-            //         }
-            //     }
-            // }
         }
 
-        // let boards = Tensor::<_, 4>::cat(input_boards, 0).to_device(device);
-        // let mines = Tensor::<_, 4>::cat(mines, 0).to_device(device);
-        let boards = Tensor::<_, 4>::cat(input_boards, 0);
-        let mines = Tensor::<_, 3>::cat(mines, 0);
+        let boards = Tensor::<_, 5>::cat(input_boards, 0);
+        let mines = Tensor::<_, 4>::cat(mines, 0);
 
-        assert_eq!(boards.dims(), [items_len, 2, ROWS, COLS]);
-        assert_eq!(mines.dims(), [items_len, ROWS, COLS]);
+        assert_eq!(boards.dims(), [items_len, 2, layers, rows, cols]);
+        assert_eq!(mines.dims(), [items_len, layers, rows, cols]);
 
-        MinesweeperBatch { boards, mines }
+        Ok(MinesweeperBatch3d { boards, mines })
     }
 }
@@ -1,17 +1,15 @@
+use std::sync::Arc;
+
 use burn::{
     config::Config,
-    data::{
-        dataloader::{batcher::Batcher, DataLoaderBuilder},
-        dataset::{Dataset, InMemDataset},
-    },
+    data::{dataloader::DataLoaderBuilder, dataset::InMemDataset},
     module::Module,
     optim::AdamConfig,
     prelude::Backend,
     record::CompactRecorder,
     tensor::{backend::AutodiffBackend, Tensor},
     train::{
-        metric::{AccuracyMetric, LossMetric},
-        LearnerBuilder, RegressionOutput, TrainOutput, TrainStep, ValidStep,
+        metric::LossMetric, LearnerBuilder, RegressionOutput, TrainOutput, TrainStep, ValidStep,
     },
 };
 use burn_cuda::Cuda;
@@ -22,7 +20,9 @@ use crate::{
         batcher::{MinesweeperBatch, MinesweeperBatcher},
         model::{Model, ModelConfig},
     },
-    game::{Minesweeper, Square},
+    game::{Difficulty, FirstClick, GameStatus, Minesweeper},
+    layout::{self, LayoutStrategy},
+    rules::Rule,
 };
 
 impl<B: AutodiffBackend> TrainStep<MinesweeperBatch<B>, RegressionOutput<B>> for Model<B> {
@@ -53,6 +53,12 @@ pub struct TrainingConfig {
     pub seed: u64,
     #[config(default = 1.0e-4)]
     pub learning_rate: f64,
+    /// Puzzle rules for every generated board, written like `"liar"` or `"lives:3"`
+    #[config(default = "Vec::new()")]
+    pub rules: Vec<String>,
+    /// How the mines of every generated board are laid out, see [`layout::from_name`]
+    #[config(default = "String::from(\"uniform\")")]
+    pub layout: String,
 }
 
 fn create_artifact_dir(artifact_dir: &str) {
@@ -70,10 +76,17 @@ pub fn train<B: AutodiffBackend>(artifact_dir: &str, config: TrainingConfig, dev
     B::seed(config.seed);
 
     let batcher = MinesweeperBatcher::default();
+    let rules = config
+        .rules
+        .iter()
+        .map(|rule| rule.parse::<Rule>())
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Rules should be valid");
+    let layout = layout::from_name(&config.layout).expect("Layout should be valid");
 
     println!("Generating training and test data...");
-    let train_data = InMemDataset::new(generate_data(10_000, config.seed));
-    let test_data = InMemDataset::new(generate_data(1_000, config.seed));
+    let train_data = InMemDataset::new(generate_data(10_000, config.seed, &rules, &layout));
+    let test_data = InMemDataset::new(generate_data(1_000, config.seed, &rules, &layout));
     println!("Done generating data");
 
     // let test_item = test_data.iter().next().unwrap();
@@ -117,22 +130,32 @@ pub fn train<B: AutodiffBackend>(artifact_dir: &str, config: TrainingConfig, dev
         .expect("Trained model should be saved successfully");
 }
 
-fn generate_data(amount: u32, seed: u64) -> Vec<Minesweeper> {
+fn generate_data(
+    amount: u32,
+    seed: u64,
+    rules: &[Rule],
+    layout: &Arc<dyn LayoutStrategy>,
+) -> Vec<Minesweeper> {
     let mut rng = SmallRng::seed_from_u64(seed);
 
     (0..amount)
         .map(|_| {
-            let mines = rng.random_range(4..100);
-            let mut game = Minesweeper::new_with_mines_seeded(mines, seed);
-
-            while !game.is_board_completed() {
-                let row = rng.random_range(0..game.grid.len());
-                let col = rng.random_range(0..game.grid[0].len());
-
-                let clicked_square = game.click(row, col);
-                if matches!(clicked_square, Square::Mine) {
-                    break;
-                }
+            let difficulty = Difficulty::ALL[rng.random_range(0..Difficulty::ALL.len())];
+            let (rows, cols, max_mines) = difficulty.dimensions();
+            let mines = rng.random_range(1..=max_mines);
+            let mut game =
+                Minesweeper::new_deferred_seeded(rows, cols, mines, FirstClick::Opening, seed)
+                    .expect("Every difficulty fits its mines")
+                    .with_rules(rules.to_vec())
+                    .with_layout(layout.clone())
+                    .expect("Deferred boards do not lay out their mines yet");
+
+            while game.status() == GameStatus::Playing {
+                let row = rng.random_range(0..game.rows());
+                let col = rng.random_range(0..game.cols());
+
+                // Clicks on opened or flagged squares do nothing
+                let _ = game.click(row, col);
             }
 
             game
@@ -140,8 +163,10 @@ fn generate_data(amount: u32, seed: u64) -> Vec<Minesweeper> {
         .collect()
 }
 
+/// For debugging the training data, see the calls in `train`
+#[allow(dead_code)]
 fn print_tensor_boards(boards: Tensor<Cuda, 4>) {
-    let [batch_size, depth, height, width] = boards.dims();
+    let [batch_size, _, height, width] = boards.dims();
 
     for b in 0..batch_size {
         println!("Board {}:", b);
@@ -169,6 +194,8 @@ fn print_tensor_boards(boards: Tensor<Cuda, 4>) {
     }
 }
 
+/// For debugging the training data, see the calls in `train`
+#[allow(dead_code)]
 fn print_tensor_mines(boards: Tensor<Cuda, 3>) {
     let [batch_size, height, width] = boards.dims();
 
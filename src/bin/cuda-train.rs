@@ -14,12 +14,20 @@ pub type CudaAutodiffBackend = Autodiff<CudaBackend>;
 fn main() {
     let device = dbg!(Default::default());
     let artifact_dir = std::env::var("ARTIFACT_DIR").unwrap_or_else(|_| "artifacts".to_string());
+    // Puzzle rules as a comma separated list, like `RULES=liar,lives:3`
+    let rules = std::env::var("RULES")
+        .map(|rules| rules.split(',').map(String::from).collect())
+        .unwrap_or_default();
+    // How the mines are laid out, like `LAYOUT=clustered:3:2`
+    let layout = std::env::var("LAYOUT").unwrap_or_else(|_| "uniform".to_string());
     train::<CudaAutodiffBackend>(
         &artifact_dir,
         TrainingConfig::new(
             ModelConfig::new(constants::COLS, constants::ROWS, 512),
             AdamConfig::new(),
-        ),
+        )
+        .with_rules(rules)
+        .with_layout(layout),
         device,
     );
 }
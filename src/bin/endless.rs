@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use minesweeper::{
+    constants::{SCREEN_HEIGHT, SCREEN_WIDTH, SQUARE_MARGIN},
+    endless::{Endless, EndlessOutcome},
+    game::{GameStatus, Square},
+};
+use nannou::prelude::*;
+
+const SQUARE_SIZE: f32 = 32.;
+/// How far the mouse has to move with a button held before it pans instead of clicking
+const DRAG_THRESHOLD: f32 = 4.;
+/// How far the arrow keys pan the camera
+const PAN_STEP: f32 = SQUARE_SIZE * 3.;
+
+fn main() {
+    nannou::app(model).update(update).run();
+}
+
+struct Model {
+    endless: Endless,
+    /// The world position in the middle of the screen
+    camera: Vec2,
+    /// Where the mouse was pressed and the camera at that time, while a button is held
+    drag: Option<(Vec2, Vec2)>,
+    dragged: bool,
+    textures: HashMap<&'static str, wgpu::Texture>,
+}
+
+fn model(app: &App) -> Model {
+    let _window = app
+        .new_window()
+        .size(SCREEN_WIDTH, SCREEN_HEIGHT)
+        .title("Bombsearcher Endless")
+        .view(view)
+        .event(event)
+        .build()
+        .unwrap();
+
+    let assets = app.assets_path().unwrap();
+    let bomb_texture = wgpu::Texture::from_path(app, assets.join("bomb.png")).unwrap();
+    let flag_texture = wgpu::Texture::from_path(app, assets.join("flag.png")).unwrap();
+
+    Model {
+        endless: Endless::new(rand::random()),
+        camera: Vec2::ZERO,
+        drag: None,
+        dragged: false,
+        textures: HashMap::from([("bomb", bomb_texture), ("flag", flag_texture)]),
+    }
+}
+
+fn screen_to_square(model: &Model, point: Vec2) -> (i64, i64) {
+    let world = point + model.camera;
+    (
+        (world.y / SQUARE_SIZE).floor() as i64,
+        (world.x / SQUARE_SIZE).floor() as i64,
+    )
+}
+
+fn square_to_screen(model: &Model, row: i64, col: i64) -> Vec2 {
+    vec2(
+        (col as f32 + 0.5) * SQUARE_SIZE,
+        (row as f32 + 0.5) * SQUARE_SIZE,
+    ) - model.camera
+}
+
+/// The rows and cols of every square that is at least partly on screen
+fn visible(app: &App, model: &Model) -> (std::ops::Range<i64>, std::ops::Range<i64>) {
+    let rect = app.window_rect();
+    let (bottom, left) = screen_to_square(model, rect.bottom_left());
+    let (top, right) = screen_to_square(model, rect.top_right());
+    (bottom..top + 1, left..right + 1)
+}
+
+fn update(app: &App, model: &mut Model, _update: Update) {
+    if let Some((pressed_at, camera)) = model.drag {
+        let moved = app.mouse.position() - pressed_at;
+        if moved.length() > DRAG_THRESHOLD {
+            model.dragged = true;
+        }
+        if model.dragged {
+            model.camera = camera - moved;
+        }
+    }
+
+    let (rows, cols) = visible(app, model);
+    model.endless.generate_visible(rows, cols);
+}
+
+fn event(app: &App, model: &mut Model, event: WindowEvent) {
+    match event {
+        WindowEvent::MousePressed(_) => {
+            model.drag = Some((app.mouse.position(), model.camera));
+            model.dragged = false;
+        }
+        // A press and release without dragging in between is a click
+        WindowEvent::MouseReleased(button) => {
+            model.drag = None;
+            if model.dragged {
+                return;
+            }
+
+            let (row, col) = screen_to_square(model, app.mouse.position());
+            let outcome = match button {
+                MouseButton::Left => model.endless.click(row, col),
+                MouseButton::Right => model.endless.mark(row, col),
+                _ => return,
+            };
+            if outcome == Ok(EndlessOutcome::HitMine) {
+                let (chunks, finished) = model.endless.chunk_count();
+                println!(
+                    "Hit a mine after opening {} squares, {finished} of {chunks} chunks finished",
+                    model.endless.opened_count()
+                );
+            }
+        }
+        WindowEvent::KeyPressed(key) => {
+            let pan = match key {
+                Key::Left | Key::A => vec2(-1., 0.),
+                Key::Right | Key::D => vec2(1., 0.),
+                Key::Up | Key::W => vec2(0., 1.),
+                Key::Down | Key::S => vec2(0., -1.),
+                Key::Home => {
+                    model.camera = Vec2::ZERO;
+                    return;
+                }
+                Key::N => {
+                    model.endless = Endless::new(rand::random());
+                    model.camera = Vec2::ZERO;
+                    return;
+                }
+                _ => return,
+            };
+            model.camera += pan * PAN_STEP;
+        }
+        _ => {}
+    }
+}
+
+fn view(app: &App, model: &Model, frame: Frame) {
+    let draw = app.draw();
+    draw.background().color(GRAY);
+
+    let endless = &model.endless;
+    let size = SQUARE_SIZE - SQUARE_MARGIN;
+    let (rows, cols) = visible(app, model);
+
+    for row in rows {
+        for col in cols.clone() {
+            let position = square_to_screen(model, row, col);
+
+            if !endless.is_square_open(row, col) {
+                // The mines are shown once the game is lost
+                let color = if endless.status() == GameStatus::Lost && endless.is_mine(row, col) {
+                    Rgb::new(0.8, 0.6, 0.6)
+                } else {
+                    Rgb::new(1., 1., 1.)
+                };
+                draw.rect().w_h(size, size).xy(position).color(color);
+
+                if endless.is_square_marked(row, col) {
+                    draw.texture(model.textures.get("flag").unwrap())
+                        .w_h(size, size)
+                        .xy(position);
+                }
+                continue;
+            }
+
+            let square_state = endless.square_state(row, col);
+            let background_color = match square_state {
+                Square::Empty | Square::Nearby(_) => Rgb::new(0.3, 0.3, 0.3),
+                Square::Mine => Rgb::new(0.8, 0.3, 0.3),
+            };
+            draw.rect()
+                .w_h(size, size)
+                .xy(position)
+                .color(background_color);
+
+            match square_state {
+                Square::Empty => {}
+                Square::Nearby(v) => {
+                    draw.text(&v.to_string())
+                        .w_h(size, size)
+                        .xy(position)
+                        .font_size(18)
+                        .color(WHITE);
+                }
+                Square::Mine => {
+                    draw.texture(model.textures.get("bomb").unwrap())
+                        .w_h(size, size)
+                        .xy(position);
+                }
+            }
+        }
+    }
+
+    draw.text(&format!("Opened: {}", endless.opened_count()))
+        .xy(app.window_rect().top_left() + vec2(100., -20.))
+        .font_size(20)
+        .color(WHITE);
+
+    draw.to_frame(app, &frame).unwrap();
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+
+use minesweeper::{
+    constants::{SCREEN_HEIGHT, SCREEN_PADDING, SQUARE_MARGIN},
+    game::Square,
+    game3d::{Minesweeper3d, MoveOutcome3d},
+};
+use nannou::prelude::*;
+
+const LAYERS: usize = 6;
+const ROWS: usize = 10;
+const COLS: usize = 10;
+const MINES: usize = 50;
+
+const SCREEN_WIDTH: u32 = 1400;
+/// Size of the current layer, the layers below and above are drawn smaller on each side
+const LAYER_SIZE: f32 = SCREEN_HEIGHT as f32 - SCREEN_PADDING * 2.;
+const SIDE_LAYER_SIZE: f32 = 250.;
+const SIDE_LAYER_X: f32 = LAYER_SIZE / 2. + SCREEN_PADDING / 2. + SIDE_LAYER_SIZE / 2.;
+/// How much of the neighbouring layers shows through
+const FADED: f32 = 0.35;
+
+fn main() {
+    nannou::app(model).run();
+}
+
+struct Model {
+    minesweeper: Minesweeper3d,
+    /// The layer being played on
+    layer: usize,
+    textures: HashMap<&'static str, wgpu::Texture>,
+}
+
+fn model(app: &App) -> Model {
+    let _window = app
+        .new_window()
+        .size(SCREEN_WIDTH, SCREEN_HEIGHT)
+        .resizable(false)
+        .title("Bombsearcher 3D")
+        .view(view)
+        .event(event)
+        .build()
+        .unwrap();
+
+    let assets = app.assets_path().unwrap();
+    let bomb_texture = wgpu::Texture::from_path(app, assets.join("bomb.png")).unwrap();
+    let flag_texture = wgpu::Texture::from_path(app, assets.join("flag.png")).unwrap();
+
+    Model {
+        minesweeper: new_game(),
+        layer: 0,
+        textures: HashMap::from([("bomb", bomb_texture), ("flag", flag_texture)]),
+    }
+}
+
+fn new_game() -> Minesweeper3d {
+    Minesweeper3d::new(LAYERS, ROWS, COLS, MINES).expect("The board fits its mines")
+}
+
+/// Where a layer is drawn, as its centre x and its size. The layer being played on is in the
+/// middle, the one below it to the left and the one above it to the right.
+fn layer_area(offset: i32) -> (f32, f32) {
+    match offset {
+        0 => (0., LAYER_SIZE),
+        _ => (SIDE_LAYER_X * offset.signum() as f32, SIDE_LAYER_SIZE),
+    }
+}
+
+fn square_size(minesweeper: &Minesweeper3d, area_size: f32) -> f32 {
+    area_size / minesweeper.rows().max(minesweeper.cols()) as f32
+}
+
+fn row_col_to_x_y(minesweeper: &Minesweeper3d, offset: i32, row: usize, col: usize) -> (f32, f32) {
+    let (center_x, area_size) = layer_area(offset);
+    let size = square_size(minesweeper, area_size);
+    (
+        center_x + size * (col as f32 + 0.5 - minesweeper.cols() as f32 / 2.),
+        size * (row as f32 + 0.5 - minesweeper.rows() as f32 / 2.),
+    )
+}
+
+/// The layer offset and square under `(x, y)`, in any of the three drawn layers
+fn x_y_to_row_col(minesweeper: &Minesweeper3d, x: f32, y: f32) -> Option<(i32, usize, usize)> {
+    (-1..=1).find_map(|offset| {
+        let (center_x, area_size) = layer_area(offset);
+        let size = square_size(minesweeper, area_size);
+        let col = ((x - center_x) / size + minesweeper.cols() as f32 / 2.).floor();
+        let row = (y / size + minesweeper.rows() as f32 / 2.).floor();
+
+        ((0. ..minesweeper.cols() as f32).contains(&col)
+            && (0. ..minesweeper.rows() as f32).contains(&row))
+        .then_some((offset, row as usize, col as usize))
+    })
+}
+
+fn change_layer(model: &mut Model, offset: i32) {
+    let layer = model.layer as i32 + offset;
+    if (0..model.minesweeper.layers() as i32).contains(&layer) {
+        model.layer = layer as usize;
+    }
+}
+
+fn event(app: &App, model: &mut Model, event: WindowEvent) {
+    match event {
+        WindowEvent::MousePressed(button @ (MouseButton::Left | MouseButton::Right)) => {
+            let Some((offset, row, col)) =
+                x_y_to_row_col(&model.minesweeper, app.mouse.x, app.mouse.y)
+            else {
+                return;
+            };
+            // Clicking one of the faded layers moves to it
+            if offset != 0 {
+                change_layer(model, offset);
+                return;
+            }
+
+            let outcome = if button == MouseButton::Left {
+                model.minesweeper.click(model.layer, row, col)
+            } else {
+                model.minesweeper.mark(model.layer, row, col)
+            };
+            match outcome {
+                Ok(MoveOutcome3d::Won) => println!("Yey"),
+                Ok(MoveOutcome3d::HitMine(layer, row, col)) => {
+                    println!("Hit the mine at ({layer}, {row}, {col})")
+                }
+                _ => {}
+            }
+        }
+        WindowEvent::MouseWheel(MouseScrollDelta::LineDelta(_, y), _) if y != 0. => {
+            change_layer(model, y.signum() as i32);
+        }
+        WindowEvent::KeyPressed(Key::Up | Key::PageUp) => change_layer(model, 1),
+        WindowEvent::KeyPressed(Key::Down | Key::PageDown) => change_layer(model, -1),
+        WindowEvent::KeyPressed(Key::N) => {
+            model.minesweeper = new_game();
+            model.layer = 0;
+        }
+        _ => {}
+    }
+}
+
+fn view(app: &App, model: &Model, frame: Frame) {
+    let draw = app.draw();
+    draw.background().color(GRAY);
+
+    for offset in -1..=1 {
+        let layer = model.layer as i32 + offset;
+        if !(0..model.minesweeper.layers() as i32).contains(&layer) {
+            continue;
+        }
+        draw_layer(&draw, model, layer as usize, offset);
+    }
+
+    draw.to_frame(app, &frame).unwrap();
+}
+
+fn draw_layer(draw: &Draw, model: &Model, layer: usize, offset: i32) {
+    let minesweeper = &model.minesweeper;
+    let (center_x, area_size) = layer_area(offset);
+    let size = square_size(minesweeper, area_size);
+    let alpha = if offset == 0 { 1. } else { FADED };
+    let font_size = (size * 0.5) as u32;
+
+    draw.text(&format!("Layer {} of {}", layer + 1, minesweeper.layers()))
+        .x_y(center_x, area_size / 2. + SCREEN_PADDING / 4.)
+        .w(area_size)
+        .font_size(if offset == 0 { 24 } else { 16 })
+        .color(WHITE);
+
+    for row in 0..minesweeper.rows() {
+        for col in 0..minesweeper.cols() {
+            let (x, y) = row_col_to_x_y(minesweeper, offset, row, col);
+            let square = size - SQUARE_MARGIN;
+
+            if !minesweeper.is_square_open(layer, row, col) {
+                draw.rect()
+                    .w_h(square, square)
+                    .x_y(x, y)
+                    .color(rgba(1., 1., 1., alpha));
+
+                if minesweeper.is_square_marked(layer, row, col) {
+                    draw.texture(model.textures.get("flag").unwrap())
+                        .w_h(square, square)
+                        .x_y(x, y);
+                }
+                continue;
+            }
+
+            let Some(square_state) = minesweeper.square_state(layer, row, col) else {
+                continue;
+            };
+            let background_color = match square_state {
+                Square::Empty | Square::Nearby(_) => rgba(0.3, 0.3, 0.3, alpha),
+                Square::Mine => rgba(0.8, 0.3, 0.3, alpha),
+            };
+            draw.rect()
+                .w_h(square, square)
+                .x_y(x, y)
+                .color(background_color);
+
+            match square_state {
+                Square::Empty => {}
+                Square::Nearby(v) => {
+                    draw.text(&v.to_string())
+                        .w_h(square, square)
+                        .x_y(x, y)
+                        .font_size(font_size)
+                        .color(rgba(1., 1., 1., alpha));
+                }
+                Square::Mine => {
+                    draw.texture(model.textures.get("bomb").unwrap())
+                        .w_h(square, square)
+                        .x_y(x, y);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! A fixed size set of bits, used to keep one bit for every square of a board.
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct BitSet {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitSet {
+    /// `len` bits, all cleared
+    pub(crate) fn new(len: usize) -> Self {
+        BitSet {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    pub(crate) fn get(&self, i: usize) -> bool {
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    pub(crate) fn set(&mut self, i: usize, value: bool) {
+        if value {
+            self.words[i / 64] |= 1 << (i % 64);
+        } else {
+            self.words[i / 64] &= !(1 << (i % 64));
+        }
+    }
+
+    /// How many bits are set
+    pub(crate) fn count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// How many bits are set in both sets
+    pub(crate) fn count_and(&self, other: &BitSet) -> usize {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| (a & b).count_ones() as usize)
+            .sum()
+    }
+
+    /// True when every bit is set in at least one of the sets
+    pub(crate) fn covers_with(&self, other: &BitSet) -> bool {
+        let full_words = self.len / 64;
+        let mut full = self.words.iter().zip(&other.words).take(full_words);
+        if !full.all(|(a, b)| a | b == u64::MAX) {
+            return false;
+        }
+
+        // The last word only uses its lowest bits
+        let rest = self.len % 64;
+        rest == 0 || {
+            let mask = (1 << rest) - 1;
+            (self.words[full_words] | other.words[full_words]) & mask == mask
+        }
+    }
+
+    /// The index of every set bit, lowest first
+    pub(crate) fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(i * 64 + bit)
+            })
+        })
+    }
+}
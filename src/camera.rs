@@ -0,0 +1,113 @@
+//! Where the board is shown in the window.
+//!
+//! The board is laid out by the app's `utils` in the window minus its padding, and the
+//! camera zooms and pans that layout. Board points are what the layout works in, and screen
+//! points are nannou's window coordinates, both with the origin in the middle.
+
+use crate::constants::{MAX_ZOOM, SCREEN_PADDING};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    width: f32,
+    height: f32,
+    zoom: f32,
+    /// The board point in the middle of the window
+    centre: (f32, f32),
+}
+
+impl Camera {
+    /// A camera showing the whole board in a window of `width` x `height`
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            width,
+            height,
+            zoom: 1.,
+            centre: (0., 0.),
+        }
+    }
+
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.width = width;
+        self.height = height;
+        self.keep_board_in_view();
+    }
+
+    /// Shows the whole board again
+    pub fn reset(&mut self) {
+        *self = Self::new(self.width, self.height);
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// The space the board is laid out in, the window without its padding
+    pub fn board_area(&self) -> (f32, f32) {
+        (
+            (self.width - SCREEN_PADDING * 2.).max(1.),
+            (self.height - SCREEN_PADDING * 2.).max(1.),
+        )
+    }
+
+    /// Zooms in by `factor`, or out when it is below 1, keeping the board point under the
+    /// screen point `(x, y)` where it is
+    pub fn zoom_at(&mut self, factor: f32, x: f32, y: f32) {
+        let (before_x, before_y) = self.to_board(x, y);
+        self.zoom = (self.zoom * factor).clamp(1., MAX_ZOOM);
+        let (after_x, after_y) = self.to_board(x, y);
+
+        self.centre.0 += before_x - after_x;
+        self.centre.1 += before_y - after_y;
+        self.keep_board_in_view();
+    }
+
+    /// Moves the board along with a drag of `(dx, dy)` screen points
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        self.centre.0 -= dx / self.zoom;
+        self.centre.1 -= dy / self.zoom;
+        self.keep_board_in_view();
+    }
+
+    /// Pans just enough to bring the board point `(x, y)` inside the window's padding
+    pub fn follow(&mut self, x: f32, y: f32) {
+        let (screen_x, screen_y) = self.to_screen(x, y);
+        let half_width = (self.width / 2. - SCREEN_PADDING).max(0.);
+        let half_height = (self.height / 2. - SCREEN_PADDING).max(0.);
+
+        self.centre.0 += (screen_x - screen_x.clamp(-half_width, half_width)) / self.zoom;
+        self.centre.1 += (screen_y - screen_y.clamp(-half_height, half_height)) / self.zoom;
+        self.keep_board_in_view();
+    }
+
+    pub fn to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            (x - self.centre.0) * self.zoom,
+            (y - self.centre.1) * self.zoom,
+        )
+    }
+
+    pub fn to_board(&self, x: f32, y: f32) -> (f32, f32) {
+        (x / self.zoom + self.centre.0, y / self.zoom + self.centre.1)
+    }
+
+    /// True when something `size` screen points across centred on the screen point `(x, y)`
+    /// is at least partly in the window
+    pub fn is_visible(&self, x: f32, y: f32, size: f32) -> bool {
+        x.abs() - size / 2. <= self.width / 2. && y.abs() - size / 2. <= self.height / 2.
+    }
+
+    /// The middle of the window never leaves the board area
+    fn keep_board_in_view(&mut self) {
+        let (width, height) = self.board_area();
+        self.centre.0 = self.centre.0.clamp(-width / 2., width / 2.);
+        self.centre.1 = self.centre.1.clamp(-height / 2., height / 2.);
+    }
+}
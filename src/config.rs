@@ -0,0 +1,414 @@
+//! Settings that are kept between launches of the app.
+//!
+//! They are saved as `key = value` lines in [`CONFIG_PATH`], lines starting with `#` are
+//! comments:
+//!
+//! ```text
+//! # Bombsearcher settings
+//! preset = expert
+//! theme = Classic
+//! keys.flag = F, Space
+//! ```
+//!
+//! A preset is `beginner`, `intermediate`, `expert`, or a custom board written as
+//! `ROWSxCOLSxMINES`, like `20x20x60`. The theme is the name of a [`Theme`].
+//!
+//! `keys.ACTION` binds a comma separated list of keys to an [`Action`], replacing its default
+//! keys. Keys are named like winit's `VirtualKeyCode`, for example `A`, `Key1`, `Space`,
+//! `Return`, `Left` or `Slash`, and case does not matter. A key bound by the config is taken
+//! away from the action it was bound to by default.
+
+use std::{collections::BTreeMap, fmt::Display, path::Path, str::FromStr};
+
+use crate::{
+    constants::{COLS, MINES, ROWS},
+    game::{check_size, Difficulty},
+    theme::Theme,
+};
+
+pub const CONFIG_PATH: &str = "minesweeper.conf";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Malformed { line: usize, reason: String },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "Could not read config: {err}"),
+            ConfigError::Malformed { line, reason } => {
+                write!(f, "Malformed config on line {line}: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+/// The size of board a new game is played on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    Standard(Difficulty),
+    Custom {
+        rows: usize,
+        cols: usize,
+        mines: usize,
+    },
+}
+
+impl Preset {
+    /// Returns `(rows, cols, mines)` for the preset
+    pub fn dimensions(&self) -> (usize, usize, usize) {
+        match *self {
+            Preset::Standard(difficulty) => difficulty.dimensions(),
+            Preset::Custom { rows, cols, mines } => (rows, cols, mines),
+        }
+    }
+}
+
+impl Default for Preset {
+    fn default() -> Self {
+        Preset::Custom {
+            rows: ROWS,
+            cols: COLS,
+            mines: MINES,
+        }
+    }
+}
+
+impl Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Preset::Standard(Difficulty::Beginner) => write!(f, "beginner"),
+            Preset::Standard(Difficulty::Intermediate) => write!(f, "intermediate"),
+            Preset::Standard(Difficulty::Expert) => write!(f, "expert"),
+            Preset::Custom { rows, cols, mines } => write!(f, "{rows}x{cols}x{mines}"),
+        }
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(difficulty) = Difficulty::ALL
+            .into_iter()
+            .find(|difficulty| Preset::Standard(*difficulty).to_string() == s)
+        {
+            return Ok(Preset::Standard(difficulty));
+        }
+
+        let [rows, cols, mines] = s
+            .split('x')
+            .map(|n| n.parse::<usize>().ok())
+            .collect::<Option<Vec<_>>>()
+            .and_then(|numbers| numbers.try_into().ok())
+            .ok_or_else(|| format!("Unknown preset {s:?}"))?;
+        check_size(rows, cols, mines).map_err(|err| format!("{err} in {s:?}"))?;
+
+        Ok(Preset::Custom { rows, cols, mines })
+    }
+}
+
+/// Something the player can do with a single key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    CursorLeft,
+    CursorRight,
+    CursorUp,
+    CursorDown,
+    Open,
+    Flag,
+    Chord,
+    NewGame,
+    Settings,
+    Theme,
+    Hint,
+    Probabilities,
+    Ai,
+    NoGuess,
+    Topology,
+    Wrap,
+    ZoomIn,
+    ZoomOut,
+    ResetView,
+}
+
+impl Action {
+    pub const ALL: [Action; 19] = [
+        Action::CursorLeft,
+        Action::CursorRight,
+        Action::CursorUp,
+        Action::CursorDown,
+        Action::Open,
+        Action::Flag,
+        Action::Chord,
+        Action::NewGame,
+        Action::Settings,
+        Action::Theme,
+        Action::Hint,
+        Action::Probabilities,
+        Action::Ai,
+        Action::NoGuess,
+        Action::Topology,
+        Action::Wrap,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::ResetView,
+    ];
+
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::CursorLeft => &["Left", "H"],
+            Action::CursorRight => &["Right", "L"],
+            Action::CursorUp => &["Up", "K"],
+            Action::CursorDown => &["Down", "J"],
+            Action::Open => &["Space", "Return"],
+            Action::Flag => &["F"],
+            Action::Chord => &["D"],
+            Action::NewGame => &["N"],
+            Action::Settings => &["O"],
+            Action::Theme => &["C"],
+            Action::Hint => &["Slash"],
+            Action::Probabilities => &["P"],
+            Action::Ai => &["M"],
+            Action::NoGuess => &["G"],
+            Action::Topology => &["T"],
+            Action::Wrap => &["W"],
+            Action::ZoomIn => &["Equals", "Plus", "NumpadAdd"],
+            Action::ZoomOut => &["Minus", "NumpadSubtract"],
+            Action::ResetView => &["Home"],
+        }
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Action::CursorLeft => "cursor_left",
+            Action::CursorRight => "cursor_right",
+            Action::CursorUp => "cursor_up",
+            Action::CursorDown => "cursor_down",
+            Action::Open => "open",
+            Action::Flag => "flag",
+            Action::Chord => "chord",
+            Action::NewGame => "new_game",
+            Action::Settings => "settings",
+            Action::Theme => "theme",
+            Action::Hint => "hint",
+            Action::Probabilities => "probabilities",
+            Action::Ai => "ai",
+            Action::NoGuess => "no_guess",
+            Action::Topology => "topology",
+            Action::Wrap => "wrap",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::ResetView => "reset_view",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::ALL
+            .into_iter()
+            .find(|action| action.to_string() == s)
+            .ok_or_else(|| format!("Unknown action {s:?}"))
+    }
+}
+
+/// The keys bound to every action
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keybindings(BTreeMap<Action, Vec<String>>);
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Keybindings(
+            Action::ALL
+                .into_iter()
+                .map(|action| {
+                    let keys = action.default_keys().iter().map(|key| key.to_string());
+                    (action, keys.collect())
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Keybindings {
+    /// The action bound to the key with this name
+    pub fn action(&self, key: &str) -> Option<Action> {
+        self.0.iter().find_map(|(action, keys)| {
+            keys.iter()
+                .any(|bound| bound.eq_ignore_ascii_case(key))
+                .then_some(*action)
+        })
+    }
+
+    pub fn keys(&self, action: Action) -> &[String] {
+        self.0.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Binds `keys` to `action` instead of its old keys, taking them away from other actions
+    pub fn bind(&mut self, action: Action, keys: Vec<String>) {
+        for bound in self.0.values_mut() {
+            bound.retain(|old| !keys.iter().any(|key| key.eq_ignore_ascii_case(old)));
+        }
+        self.0.insert(action, keys);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub preset: Preset,
+    pub theme: String,
+    pub keys: Keybindings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            preset: Preset::default(),
+            theme: Theme::default().name,
+            keys: Keybindings::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    /// Reads the config at `path`, a missing file is the default config
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        match std::fs::read_to_string(path) {
+            Ok(config) => config.parse(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl Display for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "# Bombsearcher settings")?;
+        writeln!(f, "preset = {}", self.preset)?;
+        writeln!(f, "theme = {}", self.theme)?;
+        for (action, keys) in &self.keys.0 {
+            writeln!(f, "keys.{action} = {}", keys.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = Config::default();
+        // Where every action was bound, a key can only be bound once by the config
+        let mut bound_on = BTreeMap::new();
+
+        for (i, line) in s.lines().enumerate() {
+            let line_number = i + 1;
+            let malformed = |reason: String| ConfigError::Malformed {
+                line: line_number,
+                reason,
+            };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| malformed("expected key = value".to_string()))?;
+
+            match key.trim() {
+                "preset" => config.preset = value.trim().parse().map_err(malformed)?,
+                "theme" => config.theme = value.trim().to_string(),
+                key => {
+                    let action = key
+                        .strip_prefix("keys.")
+                        .ok_or_else(|| malformed(format!("unknown setting {key:?}")))?
+                        .parse::<Action>()
+                        .map_err(malformed)?;
+                    let keys = value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|key| !key.is_empty())
+                        .map(str::to_string)
+                        .collect::<Vec<_>>();
+
+                    for key in &keys {
+                        if let Some(other) = config
+                            .keys
+                            .action(key)
+                            .filter(|other| *other != action && bound_on.contains_key(other))
+                        {
+                            return Err(malformed(format!(
+                                "{key} is already bound to {other} on line {}",
+                                bound_on[&other]
+                            )));
+                        }
+                    }
+                    config.keys.bind(action, keys);
+                    bound_on.insert(action, line_number);
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// What the app was started with on the command line
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Args {
+    /// Overrides the preset in the config
+    pub preset: Option<Preset>,
+    /// A replay to watch
+    pub replay: Option<String>,
+}
+
+impl Args {
+    /// Reads `--beginner`, `--intermediate`, `--expert`, `--custom ROWSxCOLSxMINES` and the
+    /// path of a replay from the arguments, without the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let preset = match arg.as_str() {
+                "--beginner" => Preset::Standard(Difficulty::Beginner),
+                "--intermediate" => Preset::Standard(Difficulty::Intermediate),
+                "--expert" => Preset::Standard(Difficulty::Expert),
+                "--custom" => args
+                    .next()
+                    .ok_or("--custom needs a board like 20x20x60")?
+                    .parse()?,
+                flag if flag.starts_with("--") => return Err(format!("Unknown flag {flag}")),
+                _ if parsed.replay.is_none() => {
+                    parsed.replay = Some(arg);
+                    continue;
+                }
+                _ => return Err(format!("Unexpected argument {arg}")),
+            };
+            parsed.preset = Some(preset);
+        }
+
+        Ok(parsed)
+    }
+}
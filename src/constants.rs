@@ -3,6 +3,15 @@ pub const COLS: usize = 20;
 
 pub const MINES: usize = 60;
 
+pub const NO_GUESS_MAX_ATTEMPTS: usize = 10_000;
+
+/// How many steps the probability search can take on one board before giving up
+pub const PROBABILITY_MAX_NODES: usize = 1_000_000;
+
+/// Below this density the empty squares of an endless board can join up into openings that
+/// never end
+pub const MIN_DENSITY: f32 = 0.12;
+
 pub const SCREEN_WIDTH: u32 = 1000;
 pub const SCREEN_HEIGHT: u32 = 1000;
 
@@ -10,8 +19,5 @@ pub const SCREEN_PADDING: f32 = 100.;
 
 pub const SQUARE_MARGIN: f32 = 2.;
 
-pub const SQUARE_WIDTH: f32 =
-    ((SCREEN_WIDTH as f32 - SCREEN_PADDING * 2.) / COLS as f32) - SQUARE_MARGIN;
-
-pub const SQUARE_HEIGHT: f32 =
-    ((SCREEN_HEIGHT as f32 - SCREEN_PADDING * 2.) / ROWS as f32) - SQUARE_MARGIN;
+/// How far the board can be zoomed in, 1 fits the whole board in the window
+pub const MAX_ZOOM: f32 = 20.;
@@ -0,0 +1,362 @@
+//! An endless board, made of chunks that are generated as they are reached.
+//!
+//! The world is split into `CHUNK_SIZE` x `CHUNK_SIZE` chunks. The mines of a chunk only depend
+//! on the world seed and the chunk's coordinates, so a chunk comes out the same no matter when
+//! or in which order it is generated, and numbers on the edge of a chunk can count the mines
+//! of the chunks next to it. Squares are addressed with `(row, col)` world coordinates, which
+//! can be negative. The squares around the origin never hold mines, so the game can always be
+//! started by opening `(0, 0)`.
+//!
+//! Chunks are stored as bitsets, and a chunk where every safe square is opened is finished: it
+//! only keeps its mines, as everything else about it follows from them.
+
+use std::{collections::HashMap, ops::Range};
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+use crate::{
+    bitset::BitSet,
+    constants::MIN_DENSITY,
+    game::{GameStatus, MinesweeperError, Square},
+};
+
+pub const CHUNK_SIZE: usize = 16;
+
+/// The density of [`Endless::new`]
+pub const DEFAULT_DENSITY: f32 = 0.16;
+
+const CHUNK_SQUARES: usize = CHUNK_SIZE * CHUNK_SIZE;
+
+#[derive(Clone, Debug)]
+enum ChunkState {
+    Playing {
+        opened: BitSet,
+        flagged: BitSet,
+    },
+    /// Every safe square is opened, and every mine counts as flagged
+    Finished,
+}
+
+/// Every bit set of a chunk has one bit for each of its squares, row major
+#[derive(Clone, Debug)]
+struct Chunk {
+    mines: BitSet,
+    state: ChunkState,
+}
+
+/// What a move did to the board
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EndlessOutcome {
+    /// This many squares were opened
+    Opened(usize),
+    HitMine,
+    Flagged,
+    Unflagged,
+}
+
+#[derive(Clone, Debug)]
+pub struct Endless {
+    seed: u64,
+    density: f32,
+    chunks: HashMap<(i64, i64), Chunk>,
+    status: GameStatus,
+    opened: u64,
+}
+
+/// The chunk a square is in, and its index in the chunk
+fn locate(row: i64, col: i64) -> ((i64, i64), usize) {
+    let size = CHUNK_SIZE as i64;
+    let chunk = (row.div_euclid(size), col.div_euclid(size));
+    let i = row.rem_euclid(size) as usize * CHUNK_SIZE + col.rem_euclid(size) as usize;
+    (chunk, i)
+}
+
+fn neighbours(row: i64, col: i64) -> impl Iterator<Item = (i64, i64)> {
+    (-1..=1)
+        .flat_map(move |r| (-1..=1).map(move |c| (row + r, col + c)))
+        .filter(move |&square| square != (row, col))
+}
+
+/// Mixes the world seed and the chunk coordinates into the seed of a single chunk
+fn chunk_seed(seed: u64, (chunk_row, chunk_col): (i64, i64)) -> u64 {
+    let mut x = seed
+        ^ (chunk_row as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (chunk_col as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    x ^= x >> 33;
+    x
+}
+
+impl Endless {
+    pub fn new(seed: u64) -> Self {
+        Self::with_density(seed, DEFAULT_DENSITY).expect("The default density is allowed")
+    }
+
+    /// `density` is the share of squares that are mines, at least [`MIN_DENSITY`] and below 1
+    pub fn with_density(seed: u64, density: f32) -> Result<Self, MinesweeperError> {
+        if !(MIN_DENSITY..1.).contains(&density) {
+            return Err(MinesweeperError::InvalidDensity);
+        }
+
+        Ok(Endless {
+            seed,
+            density,
+            chunks: HashMap::new(),
+            status: GameStatus::Playing,
+            opened: 0,
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn density(&self) -> f32 {
+        self.density
+    }
+
+    /// Only ever `Playing` or `Lost`, an endless board can not be won
+    pub fn status(&self) -> GameStatus {
+        self.status
+    }
+
+    /// How many squares have been opened in total, the score of the game
+    pub fn opened_count(&self) -> u64 {
+        self.opened
+    }
+
+    /// How many chunks are generated, and how many of them are finished
+    pub fn chunk_count(&self) -> (usize, usize) {
+        let finished = self
+            .chunks
+            .values()
+            .filter(|chunk| matches!(chunk.state, ChunkState::Finished))
+            .count();
+        (self.chunks.len(), finished)
+    }
+
+    /// The mines of a chunk, the same every time for the same seed
+    fn generate_mines(&self, chunk: (i64, i64)) -> BitSet {
+        let mut rng = SmallRng::seed_from_u64(chunk_seed(self.seed, chunk));
+        let (first_row, first_col) = (chunk.0 * CHUNK_SIZE as i64, chunk.1 * CHUNK_SIZE as i64);
+
+        let mut free = (0..CHUNK_SQUARES)
+            .filter(|i| {
+                let row = first_row + (i / CHUNK_SIZE) as i64;
+                let col = first_col + (i % CHUNK_SIZE) as i64;
+                row.abs() > 1 || col.abs() > 1
+            })
+            .collect::<Vec<_>>();
+        let amount = (self.density * CHUNK_SQUARES as f32).round() as usize;
+        let amount = amount.min(free.len());
+
+        let mut mines = BitSet::new(CHUNK_SQUARES);
+        for i in 0..amount {
+            let j = rng.random_range(i..free.len());
+            free.swap(i, j);
+            mines.set(free[i], true);
+        }
+
+        mines
+    }
+
+    fn chunk(&mut self, chunk: (i64, i64)) -> &mut Chunk {
+        if !self.chunks.contains_key(&chunk) {
+            let mines = self.generate_mines(chunk);
+            self.chunks.insert(
+                chunk,
+                Chunk {
+                    mines,
+                    state: ChunkState::Playing {
+                        opened: BitSet::new(CHUNK_SQUARES),
+                        flagged: BitSet::new(CHUNK_SQUARES),
+                    },
+                },
+            );
+        }
+
+        self.chunks.get_mut(&chunk).unwrap()
+    }
+
+    /// Generates every chunk touching the squares in view, so they are ready to be drawn
+    pub fn generate_visible(&mut self, rows: Range<i64>, cols: Range<i64>) {
+        let size = CHUNK_SIZE as i64;
+        for chunk_row in rows.start.div_euclid(size)..=(rows.end - 1).div_euclid(size) {
+            for chunk_col in cols.start.div_euclid(size)..=(cols.end - 1).div_euclid(size) {
+                self.chunk((chunk_row, chunk_col));
+            }
+        }
+    }
+
+    pub fn is_mine(&self, row: i64, col: i64) -> bool {
+        let (chunk, i) = locate(row, col);
+        match self.chunks.get(&chunk) {
+            Some(chunk) => chunk.mines.get(i),
+            None => self.generate_mines(chunk).get(i),
+        }
+    }
+
+    pub fn is_square_open(&self, row: i64, col: i64) -> bool {
+        let (chunk, i) = locate(row, col);
+        match self.chunks.get(&chunk) {
+            Some(Chunk {
+                state: ChunkState::Playing { opened, .. },
+                ..
+            }) => opened.get(i),
+            Some(Chunk {
+                mines,
+                state: ChunkState::Finished,
+            }) => !mines.get(i),
+            None => false,
+        }
+    }
+
+    pub fn is_square_marked(&self, row: i64, col: i64) -> bool {
+        let (chunk, i) = locate(row, col);
+        match self.chunks.get(&chunk) {
+            Some(Chunk {
+                state: ChunkState::Playing { flagged, .. },
+                ..
+            }) => flagged.get(i),
+            Some(Chunk {
+                mines,
+                state: ChunkState::Finished,
+            }) => mines.get(i),
+            None => false,
+        }
+    }
+
+    pub fn square_state(&self, row: i64, col: i64) -> Square {
+        if self.is_mine(row, col) {
+            return Square::Mine;
+        }
+
+        match neighbours(row, col)
+            .filter(|&(r, c)| self.is_mine(r, c))
+            .count()
+        {
+            0 => Square::Empty,
+            n => Square::Nearby(n as u32),
+        }
+    }
+
+    /// Opens a square, flood filling from it if it is empty. The flood fill generates the
+    /// chunks it reaches.
+    pub fn click(&mut self, row: i64, col: i64) -> Result<EndlessOutcome, MinesweeperError> {
+        if self.status != GameStatus::Playing {
+            return Err(MinesweeperError::GameOver);
+        }
+        if self.is_square_open(row, col) {
+            return Err(MinesweeperError::AlreadyOpen);
+        }
+        if self.is_square_marked(row, col) {
+            return Err(MinesweeperError::Flagged);
+        }
+
+        let mut opened = 0;
+        let mut touched = Vec::new();
+        let mut stack = vec![(row, col)];
+        while let Some((r, c)) = stack.pop() {
+            // Numbers on the edge need the chunks around them
+            for (n_row, n_col) in neighbours(r, c) {
+                self.chunk(locate(n_row, n_col).0);
+            }
+
+            let (chunk, i) = locate(r, c);
+            let ChunkState::Playing {
+                opened: bits,
+                flagged,
+            } = &mut self.chunk(chunk).state
+            else {
+                continue;
+            };
+            if bits.get(i) || flagged.get(i) {
+                continue;
+            }
+            bits.set(i, true);
+            opened += 1;
+            if !touched.contains(&chunk) {
+                touched.push(chunk);
+            }
+
+            if matches!(self.square_state(r, c), Square::Empty) {
+                stack.extend(neighbours(r, c));
+            }
+        }
+        self.opened += opened;
+
+        if self.is_mine(row, col) {
+            self.status = GameStatus::Lost;
+            return Ok(EndlessOutcome::HitMine);
+        }
+
+        for chunk in touched {
+            self.finish_if_done(chunk);
+        }
+
+        Ok(EndlessOutcome::Opened(opened as usize))
+    }
+
+    /// Drops everything but the mines of a chunk once all its safe squares are opened
+    fn finish_if_done(&mut self, chunk: (i64, i64)) {
+        let chunk = self.chunk(chunk);
+        let ChunkState::Playing { opened, .. } = &chunk.state else {
+            return;
+        };
+
+        if opened.covers_with(&chunk.mines) {
+            chunk.state = ChunkState::Finished;
+        }
+    }
+
+    /// Flags or unflags a closed square
+    pub fn mark(&mut self, row: i64, col: i64) -> Result<EndlessOutcome, MinesweeperError> {
+        if self.status != GameStatus::Playing {
+            return Err(MinesweeperError::GameOver);
+        }
+
+        let (chunk, i) = locate(row, col);
+        let ChunkState::Playing { opened, flagged } = &mut self.chunk(chunk).state else {
+            // A finished chunk has nothing left to flag
+            return Err(MinesweeperError::AlreadyOpen);
+        };
+        if opened.get(i) {
+            return Err(MinesweeperError::AlreadyOpen);
+        }
+
+        let marked = !flagged.get(i);
+        flagged.set(i, marked);
+        if marked {
+            Ok(EndlessOutcome::Flagged)
+        } else {
+            Ok(EndlessOutcome::Unflagged)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_on_opened_squares_are_errors() {
+        let mut endless = Endless::new(7);
+        assert!(matches!(endless.click(0, 0), Ok(EndlessOutcome::Opened(_))));
+        assert_eq!(endless.click(0, 0), Err(MinesweeperError::AlreadyOpen));
+        assert_eq!(endless.mark(0, 0), Err(MinesweeperError::AlreadyOpen));
+
+        let mine = (2..)
+            .map(|col| (0, col))
+            .find(|&(row, col)| endless.is_mine(row, col))
+            .unwrap();
+        assert_eq!(endless.mark(mine.0, mine.1), Ok(EndlessOutcome::Flagged));
+        assert_eq!(
+            endless.click(mine.0, mine.1),
+            Err(MinesweeperError::Flagged)
+        );
+        assert_eq!(endless.mark(mine.0, mine.1), Ok(EndlessOutcome::Unflagged));
+        assert_eq!(endless.click(mine.0, mine.1), Ok(EndlessOutcome::HitMine));
+        assert_eq!(endless.click(0, 1), Err(MinesweeperError::GameOver));
+    }
+}
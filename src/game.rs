@@ -1,171 +1,1374 @@
-use std::fmt::Display;
+use std::{
+    fmt::{Debug, Display},
+    ops::Range,
+    sync::{
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
-use crate::constants::*;
+use crate::{
+    bitset::BitSet,
+    constants::*,
+    layout::{LayoutStrategy, Relocating, Uniform},
+    rules::{Rule, RuleSet},
+    solver,
+    text::{number_symbol, TextError},
+    topology::Topology,
+};
 use rand::{rngs::SmallRng, Rng, SeedableRng};
 
-#[derive(Clone, Copy, Debug)]
+#[cfg(feature = "serde")]
+use crate::{
+    layout,
+    text::{parse_symbol, parse_visible, split_rows},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Square {
     Empty,
     Nearby(u32),
     Mine,
 }
 
+/// The classic board presets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Difficulty {
+    Beginner,
+    Intermediate,
+    Expert,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [
+        Difficulty::Beginner,
+        Difficulty::Intermediate,
+        Difficulty::Expert,
+    ];
+
+    /// Returns `(rows, cols, mines)` for the preset
+    pub fn dimensions(&self) -> (usize, usize, usize) {
+        match self {
+            Difficulty::Beginner => (9, 9, 10),
+            Difficulty::Intermediate => (16, 16, 40),
+            Difficulty::Expert => (16, 30, 99),
+        }
+    }
+}
+
+/// How mines are kept away from the first click on a board created with
+/// [`Minesweeper::new_deferred`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FirstClick {
+    /// The clicked square is never a mine
+    Safe,
+    /// The clicked square and all its neighbours are free of mines, so it always opens up. On
+    /// a board too full for that, only the clicked square is kept free.
+    Opening,
+    /// Mines are laid out without looking at the click, and a mine under it is moved to the
+    /// top left most free square. This is how the classic Windows game does it.
+    MoveToCorner,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameStatus {
+    Playing,
+    Lost,
+    Won,
+}
+
+/// What a move did to the board
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MoveOutcome {
+    /// Squares were opened and the game goes on. The range is read with
+    /// [`Minesweeper::opened_squares`] until the next move.
+    Opened(Range<usize>),
+    /// The mine at this square was opened and the game is lost
+    HitMine(usize, usize),
+    /// The mine at this square was opened, but there were lives left to survive it
+    LostLife(usize, usize),
+    /// The last safe square was opened
+    Won,
+    Flagged,
+    Unflagged,
+}
+
+/// Something that happened on the board, sent to every observer added with
+/// [`Minesweeper::subscribe`] or [`Minesweeper::events`]. A move that opens squares sends
+/// [`GameEvent::Flooded`] first, then every square on its own, then how the game ended.
+/// Undoing a move sends [`GameEvent::Closed`] or [`GameEvent::FlagToggled`], and redoing it
+/// sends the same events as the move did.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameEvent {
+    /// Every square one move opened, in the order they were opened
+    Flooded(Vec<(usize, usize)>),
+    /// A square was opened, showing `square`
+    Opened {
+        row: usize,
+        col: usize,
+        square: Square,
+    },
+    /// A mine was opened. The game is lost unless there were lives left.
+    MineHit {
+        row: usize,
+        col: usize,
+    },
+    /// A flag was placed, or taken away when `flagged` is false
+    FlagToggled {
+        row: usize,
+        col: usize,
+        flagged: bool,
+    },
+    /// Every square an undone move opened, closed again. The game goes on after it.
+    Closed(Vec<(usize, usize)>),
+    Won,
+    Lost,
+}
+
+type Observer = Box<dyn FnMut(&GameEvent) + Send + Sync>;
+
+/// Observers are not cloned with the board, a clone starts without any
+#[derive(Default)]
+struct Observers(Vec<Observer>);
+
+impl Clone for Observers {
+    fn clone(&self) -> Self {
+        Observers::default()
+    }
+}
+
+impl Debug for Observers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} observers", self.0.len())
+    }
+}
+
+/// Why a board could not be created or a move could not be made. Nothing about the board
+/// changes when an error is returned.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MinesweeperError {
+    /// A board needs at least one row and one column
+    EmptyBoard,
+    /// There has to be at least one square without a mine
+    TooManyMines {
+        mines: usize,
+        squares: usize,
+    },
+    /// The layout rows are not all as long as the first one
+    UnevenLayout(usize),
+    /// No layout strategy has this name, see [`crate::layout::from_name`]
+    UnknownLayout(String),
+    /// A density map weight is negative or not finite
+    InvalidWeight {
+        row: usize,
+        col: usize,
+    },
+    /// The square is not on the board
+    OutOfBounds {
+        row: usize,
+        col: usize,
+    },
+    /// The square is not on the 3D board
+    OutOfBounds3d {
+        layer: usize,
+        row: usize,
+        col: usize,
+    },
+    /// Fewer squares are left free by the first click than there are mines
+    NoRoomForMines {
+        mines: usize,
+        free: usize,
+    },
+    /// The layout strategy did not place every mine on its own free square of the board
+    BadLayout(String),
+    /// A saved board holds another amount of mines than it was saved with
+    WrongMineCount {
+        mines: usize,
+        on_board: usize,
+    },
+    /// Endless boards need a density of at least [`MIN_DENSITY`] and below 1
+    InvalidDensity,
+    /// The board is too small for its topology to wrap around
+    TooSmallToWrap,
+    /// The game is already lost or won
+    GameOver,
+    AlreadyOpen,
+    /// Flagged squares have to be unflagged before they can be opened
+    Flagged,
+    /// All flags allowed by [`Rule::FlagCap`] are placed
+    NoFlagsLeft,
+    /// Chording only works on opened numbers with as many flags around them as the most mines
+    /// they can mean
+    NotSatisfied,
+    /// None of this many boards could be solved without guessing
+    NoSolvableBoard(usize),
+    /// The board could not be read as text
+    Text(TextError),
+    /// A training batch needs at least one board
+    EmptyBatch,
+}
+
+impl Display for MinesweeperError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MinesweeperError::EmptyBoard => write!(f, "The board has no squares"),
+            MinesweeperError::TooManyMines { mines, squares } => write!(
+                f,
+                "{mines} mines do not fit on {squares} squares, one has to be free"
+            ),
+            MinesweeperError::UnevenLayout(row) => {
+                write!(f, "Row {row} is not as long as the first row")
+            }
+            MinesweeperError::UnknownLayout(name) => write!(f, "Unknown layout {name:?}"),
+            MinesweeperError::InvalidWeight { row, col } => {
+                write!(f, "The weight at ({row}, {col}) is negative or not finite")
+            }
+            MinesweeperError::OutOfBounds { row, col } => {
+                write!(f, "({row}, {col}) is not on the board")
+            }
+            MinesweeperError::OutOfBounds3d { layer, row, col } => {
+                write!(f, "({layer}, {row}, {col}) is not on the board")
+            }
+            MinesweeperError::NoRoomForMines { mines, free } => {
+                write!(f, "{mines} mines do not fit on the {free} free squares")
+            }
+            MinesweeperError::BadLayout(layout) => write!(
+                f,
+                "{layout} did not place every mine on its own free square"
+            ),
+            MinesweeperError::WrongMineCount { mines, on_board } => write!(
+                f,
+                "The board was saved with {mines} mines, but {on_board} are on it"
+            ),
+            MinesweeperError::InvalidDensity => write!(
+                f,
+                "The density has to be at least {MIN_DENSITY} and below 1"
+            ),
+            MinesweeperError::TooSmallToWrap => {
+                write!(f, "The board is too small to wrap around")
+            }
+            MinesweeperError::GameOver => write!(f, "The game is over"),
+            MinesweeperError::AlreadyOpen => write!(f, "The square is already open"),
+            MinesweeperError::Flagged => write!(f, "The square is flagged"),
+            MinesweeperError::NoFlagsLeft => write!(f, "No flags left"),
+            MinesweeperError::NotSatisfied => {
+                write!(
+                    f,
+                    "Chording needs an opened number with all of its mines flagged"
+                )
+            }
+            MinesweeperError::NoSolvableBoard(attempts) => write!(
+                f,
+                "None of {attempts} boards could be solved without guessing"
+            ),
+            MinesweeperError::EmptyBatch => write!(f, "The batch has no boards"),
+            MinesweeperError::Text(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for MinesweeperError {}
+
+impl From<TextError> for MinesweeperError {
+    fn from(err: TextError) -> Self {
+        MinesweeperError::Text(err)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MoveKind {
+    Open,
+    Flag,
+    Unflag,
+    Chord,
+}
+
+/// A move in the history. The squares it opened are kept by the board, see
+/// [`Minesweeper::opened_by`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Move {
+    pub kind: MoveKind,
+    pub row: usize,
+    pub col: usize,
+    /// Where the opened squares are in the board's `squares`
+    opened: Range<usize>,
+    /// Time since the first move of the game
+    pub at: Duration,
+}
+
+/// How a game went, see [`Minesweeper::stats`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameStats {
+    /// Time from the first move to the last, see [`Minesweeper::elapsed`]
+    pub time: Duration,
+    /// Moves made, flags included
+    pub moves: usize,
+    pub three_bv: usize,
+    /// Safe squares opened, out of all `safe` squares
+    pub opened: usize,
+    pub safe: usize,
+    pub flags: usize,
+    /// Flags on mines
+    pub correct_flags: usize,
+}
+
+/// Serialized as a [`SavedGame`]
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "SavedGame", try_from = "SavedGame")
+)]
 pub struct Minesweeper {
-    pub grid: [[Square; COLS]; ROWS],
-    pub opened: [[bool; COLS]; ROWS],
-    pub marked: [[bool; COLS]; ROWS],
+    /// One bit for every square, row major, like `opened` and `marked`
+    mined: BitSet,
+    opened: BitSet,
+    marked: BitSet,
+    /// The number on every square that is not a mine, before any lies
+    counts: Vec<u8>,
+    /// With [`Rule::Liar`], the squares showing one more than their count instead of one less
+    lies: BitSet,
+    rows: usize,
+    cols: usize,
+    mines: usize,
+    seed: u64,
+    first_click: Option<FirstClick>,
+    mines_placed: bool,
+    /// The mines are laid out by `layout` from `seed`, or will be on the first click. Boards
+    /// made from a list of mines or read from text can not be laid out again.
+    mines_from_seed: bool,
+    laid_out_around: Option<(usize, usize)>,
+    status: GameStatus,
+    topology: Topology,
+    rules: Vec<Rule>,
+    layout: Arc<dyn LayoutStrategy>,
+    /// The mines picked to count twice by [`Rule::DoubleMines`]
+    doubled: BitSet,
+    /// Read from a visible board, the numbers are kept as written and do not come from the
+    /// mines
+    numbers_as_written: bool,
+    /// Kept between flood fills, so opening squares does not allocate
+    stack: Vec<usize>,
+    /// The squares opened by every move in `history`, followed by those of `undone` from the
+    /// last one to the first. The squares of a move that is not recorded are kept until the
+    /// next move.
+    squares: Vec<(usize, usize)>,
+    keep_history: bool,
+    history: Vec<Move>,
+    /// Undone moves, the last one is redone first
+    undone: Vec<Move>,
+    started: Option<Instant>,
+    observers: Observers,
 }
 
 impl Minesweeper {
-    pub fn new_with_mines(mines: usize) -> Self {
-        Self::new_with_mines_seeded(mines, rand::random::<u64>())
+    /// Creates a `ROWS` x `COLS` board
+    pub fn new_with_mines(mines: usize) -> Result<Self, MinesweeperError> {
+        Self::new(ROWS, COLS, mines)
     }
 
-    pub fn new_with_mines_seeded(mines: usize, seed: u64) -> Self {
-        assert!(mines < COLS * ROWS);
+    /// Creates a `ROWS` x `COLS` board
+    pub fn new_with_mines_seeded(mines: usize, seed: u64) -> Result<Self, MinesweeperError> {
+        Self::new_seeded(ROWS, COLS, mines, seed)
+    }
 
-        let mut mines_left = mines;
-        let mut rng = SmallRng::seed_from_u64(seed);
-        let mut grid = [[Square::Empty; COLS]; ROWS];
+    pub fn new(rows: usize, cols: usize, mines: usize) -> Result<Self, MinesweeperError> {
+        Self::new_seeded(rows, cols, mines, rand::random::<u64>())
+    }
 
-        loop {
-            if mines_left == 0 {
-                break;
+    pub fn from_difficulty(difficulty: Difficulty) -> Self {
+        let (rows, cols, mines) = difficulty.dimensions();
+        Self::new(rows, cols, mines).expect("Every difficulty fits its mines")
+    }
+
+    pub fn new_seeded(
+        rows: usize,
+        cols: usize,
+        mines: usize,
+        seed: u64,
+    ) -> Result<Self, MinesweeperError> {
+        check_size(rows, cols, mines)?;
+
+        let mut minesweeper = Self::empty(rows, cols, mines, seed, None);
+        minesweeper.place_mines(&Uniform, |_, _| false)?;
+        Ok(minesweeper)
+    }
+
+    /// Creates a board where the mines are laid out on the first click, following `first_click`
+    pub fn new_deferred(
+        rows: usize,
+        cols: usize,
+        mines: usize,
+        first_click: FirstClick,
+    ) -> Result<Self, MinesweeperError> {
+        Self::new_deferred_seeded(rows, cols, mines, first_click, rand::random::<u64>())
+    }
+
+    /// Same seed and same first click will always give the same board
+    pub fn new_deferred_seeded(
+        rows: usize,
+        cols: usize,
+        mines: usize,
+        first_click: FirstClick,
+        seed: u64,
+    ) -> Result<Self, MinesweeperError> {
+        check_size(rows, cols, mines)?;
+
+        Ok(Self::empty(rows, cols, mines, seed, Some(first_click)))
+    }
+
+    /// Rerolls a deferred board until it can be solved from a first click on `(row, col)` by
+    /// deduction alone. Returns the board with its mines placed, and how many attempts it took.
+    ///
+    /// Attempt `n` uses `seed + n - 1`, and the board keeps the seed that worked.
+    pub fn new_no_guess_seeded(
+        rows: usize,
+        cols: usize,
+        mines: usize,
+        row: usize,
+        col: usize,
+        seed: u64,
+        max_attempts: usize,
+    ) -> Result<(Self, usize), MinesweeperError> {
+        Self::new_deferred_seeded(rows, cols, mines, FirstClick::Opening, seed)?
+            .reroll_until_no_guess(row, col, max_attempts)
+    }
+
+    /// Same as [`Minesweeper::new_no_guess_seeded`], keeping everything about this board but
+    /// its seed. Boards that were not deferred get the [`FirstClick::Opening`] policy.
+    pub fn reroll_until_no_guess(
+        &self,
+        row: usize,
+        col: usize,
+        max_attempts: usize,
+    ) -> Result<(Self, usize), MinesweeperError> {
+        for attempt in 1..=max_attempts {
+            let mut minesweeper = self.restart(self.seed.wrapping_add(attempt as u64 - 1));
+            minesweeper.lay_out_mines(row, col)?;
+
+            if solver::is_solvable(&minesweeper, row, col) {
+                return Ok((minesweeper, attempt));
+            }
+        }
+
+        Err(MinesweeperError::NoSolvableBoard(max_attempts))
+    }
+
+    /// A new deferred board with the same settings as this one
+    pub fn restart(&self, seed: u64) -> Self {
+        let first_click = self.first_click.unwrap_or(FirstClick::Opening);
+        let mut minesweeper =
+            Self::empty(self.rows, self.cols, self.mines, seed, Some(first_click));
+        minesweeper.topology = self.topology.clone();
+        minesweeper.rules = self.rules.clone();
+        minesweeper.layout = self.layout.clone();
+        minesweeper.keep_history = self.keep_history;
+        minesweeper
+    }
+
+    pub fn new_no_guess(
+        rows: usize,
+        cols: usize,
+        mines: usize,
+        row: usize,
+        col: usize,
+    ) -> Result<(Self, usize), MinesweeperError> {
+        Self::new_no_guess_seeded(
+            rows,
+            cols,
+            mines,
+            row,
+            col,
+            rand::random::<u64>(),
+            NO_GUESS_MAX_ATTEMPTS,
+        )
+    }
+
+    /// Creates a board with mines where `layout` is true
+    pub fn from_mine_layout(layout: &[Vec<bool>]) -> Result<Self, MinesweeperError> {
+        let rows = layout.len();
+        let cols = layout.first().map_or(0, Vec::len);
+        if let Some(row) = layout
+            .iter()
+            .position(|layout_row| layout_row.len() != cols)
+        {
+            return Err(MinesweeperError::UnevenLayout(row));
+        }
+        let mines = layout.iter().flatten().filter(|&&mine| mine).count();
+        check_size(rows, cols, mines)?;
+
+        let mut minesweeper = Self::empty(rows, cols, mines, 0, None);
+        for (row, layout_row) in layout.iter().enumerate() {
+            for (col, &mine) in layout_row.iter().enumerate() {
+                minesweeper.mined.set(row * cols + col, mine);
+            }
+        }
+        minesweeper.update_numbers();
+        minesweeper.mines_placed = true;
+        minesweeper.mines_from_seed = false;
+
+        Ok(minesweeper)
+    }
+
+    /// Creates a board in the middle of a game, as parsed from text. The numbers in `grid` are
+    /// kept as they are, `numbers_as_written` tells that they do not come from the mines.
+    pub(crate) fn from_parts(
+        grid: Vec<Vec<Square>>,
+        opened: Vec<Vec<bool>>,
+        marked: Vec<Vec<bool>>,
+        mines: usize,
+        topology: Topology,
+        numbers_as_written: bool,
+    ) -> Self {
+        let mut minesweeper = Self::empty(grid.len(), grid[0].len(), 0, 0, None);
+        minesweeper.topology = topology;
+        minesweeper.numbers_as_written = numbers_as_written;
+        minesweeper.mines = mines;
+        minesweeper.mines_placed = true;
+        minesweeper.mines_from_seed = false;
+
+        let squares = grid.into_iter().flatten().enumerate();
+        for (i, square) in squares {
+            match square {
+                Square::Empty => {}
+                Square::Nearby(n) => minesweeper.counts[i] = u8::try_from(n).unwrap_or(u8::MAX),
+                Square::Mine => minesweeper.mined.set(i, true),
+            }
+        }
+        for (i, opened) in opened.into_iter().flatten().enumerate() {
+            minesweeper.opened.set(i, opened);
+        }
+        for (i, marked) in marked.into_iter().flatten().enumerate() {
+            minesweeper.marked.set(i, marked);
+        }
+
+        minesweeper.update_status(minesweeper.mines_hit() > 0);
+
+        minesweeper
+    }
+
+    fn empty(
+        rows: usize,
+        cols: usize,
+        mines: usize,
+        seed: u64,
+        first_click: Option<FirstClick>,
+    ) -> Self {
+        let squares = rows * cols;
+        Minesweeper {
+            mined: BitSet::new(squares),
+            opened: BitSet::new(squares),
+            marked: BitSet::new(squares),
+            counts: vec![0; squares],
+            lies: BitSet::new(squares),
+            rows,
+            cols,
+            mines,
+            seed,
+            first_click,
+            mines_placed: false,
+            mines_from_seed: true,
+            laid_out_around: None,
+            status: GameStatus::Playing,
+            topology: Topology::default(),
+            rules: Vec::new(),
+            layout: Arc::new(Uniform),
+            doubled: BitSet::new(squares),
+            numbers_as_written: false,
+            // A square is opened once, so moves never need more room than this
+            stack: Vec::with_capacity(squares),
+            squares: Vec::with_capacity(squares),
+            keep_history: true,
+            history: Vec::new(),
+            undone: Vec::new(),
+            started: None,
+            observers: Observers::default(),
+        }
+    }
+
+    /// Places all mines on squares where `excluded` is false, following `layout`. Fails if
+    /// they do not fit, or if the strategy does not place every mine on its own free square.
+    fn place_mines(
+        &mut self,
+        layout: &dyn LayoutStrategy,
+        excluded: impl Fn(usize, usize) -> bool,
+    ) -> Result<(), MinesweeperError> {
+        let free = (0..self.rows)
+            .flat_map(|row| (0..self.cols).map(move |col| (row, col)))
+            .filter(|&(row, col)| !excluded(row, col))
+            .count();
+        if self.mines > free {
+            return Err(MinesweeperError::NoRoomForMines {
+                mines: self.mines,
+                free,
+            });
+        }
+
+        let mut rng = SmallRng::seed_from_u64(self.seed);
+        let mut mined = BitSet::new(self.rows * self.cols);
+        for (row, col) in layout.lay_out(self.rows, self.cols, self.mines, &excluded, &mut rng) {
+            if row >= self.rows || col >= self.cols || excluded(row, col) {
+                return Err(MinesweeperError::BadLayout(format!("{layout:?}")));
+            }
+            mined.set(row * self.cols + col, true);
+        }
+        if mined.count() != self.mines {
+            return Err(MinesweeperError::BadLayout(format!("{layout:?}")));
+        }
+
+        self.mined = mined;
+        self.update_numbers();
+        self.mines_placed = true;
+        self.mines_from_seed = true;
+        Ok(())
+    }
+
+    /// Lays out the mines of a deferred board as if `(row, col)` was the first click, following
+    /// its first click policy. Does nothing once the mines are placed.
+    pub fn lay_out_mines(&mut self, row: usize, col: usize) -> Result<(), MinesweeperError> {
+        self.check_square(row, col)?;
+        if self.mines_placed {
+            return Ok(());
+        }
+
+        let layout = self.layout.clone();
+        match self.first_click {
+            Some(FirstClick::Safe) => self.place_mines(&*layout, |r, c| (r, c) == (row, col))?,
+            Some(FirstClick::Opening) => {
+                let mut opening = self.neighbours(row, col).collect::<Vec<_>>();
+                opening.push((row, col));
+                opening.sort_unstable();
+                opening.dedup();
+                if self.rows * self.cols - opening.len() < self.mines {
+                    opening = vec![(row, col)];
+                }
+                self.place_mines(&*layout, |r, c| opening.contains(&(r, c)))?
             }
-            let new_pos_row = rng.random_range(0..ROWS);
-            let new_pos_col = rng.random_range(0..COLS);
+            Some(FirstClick::MoveToCorner) => {
+                self.place_mines(&Relocating(layout), |r, c| (r, c) == (row, col))?
+            }
+            None => self.place_mines(&*layout, |_, _| false)?,
+        }
+        self.laid_out_around = Some((row, col));
+        Ok(())
+    }
+
+    fn check_square(&self, row: usize, col: usize) -> Result<(), MinesweeperError> {
+        if row >= self.rows || col >= self.cols {
+            return Err(MinesweeperError::OutOfBounds { row, col });
+        }
+        Ok(())
+    }
+
+    /// Recalculates the number on every square that is not a mine, following the rules
+    fn update_numbers(&mut self) {
+        self.pick_doubled();
+        self.counts.fill(0);
 
-            let pos = &mut grid[new_pos_row][new_pos_col];
+        if self.topology.kernel.is_symmetric() {
+            let orthogonal = self.rules.counts_orthogonally();
+            // Every mine adds to the numbers around it, which is much cheaper than counting the
+            // mines around every square
+            for i in 0..self.rows * self.cols {
+                if !self.mined.get(i) {
+                    continue;
+                }
+
+                let (row, col) = (i / self.cols, i % self.cols);
+                let weight = self.weight(i) as u8;
+                let neighbours = self.topology.neighbours(self.rows, self.cols, row, col);
+                for (n_row, n_col) in
+                    neighbours.filter(|&(r, c)| !orthogonal || r == row || c == col)
+                {
+                    let n = n_row * self.cols + n_col;
+                    self.counts[n] = self.counts[n].saturating_add(weight);
+                }
+            }
+        } else {
+            for row in 0..self.rows {
+                for col in 0..self.cols {
+                    let nearby = self.mines_around(row, col);
+                    self.counts[row * self.cols + col] = u8::try_from(nearby).unwrap_or(u8::MAX);
+                }
+            }
+        }
 
-            if matches!(pos, Square::Mine) {
+        // Lies come from their own stream, so they do not change where the mines are
+        let mut lies = SmallRng::seed_from_u64(!self.seed);
+        let liar = self.rules.has_lies();
+        for i in 0..self.rows * self.cols {
+            if self.mined.get(i) {
+                self.counts[i] = 0;
                 continue;
             }
 
-            *pos = Square::Mine;
-            mines_left -= 1;
+            self.lies
+                .set(i, liar && (self.counts[i] == 0 || lies.random()));
+        }
+    }
 
-            for row_delta in -1..=1 {
-                for col_delta in -1..=1 {
-                    if row_delta == 0 && col_delta == 0
-                        || row_delta > new_pos_row as i32
-                        || col_delta > new_pos_col as i32
-                    {
-                        continue;
-                    }
+    /// Picks the mines that count twice, the same ones every time for the same seed and layout
+    fn pick_doubled(&mut self) {
+        let amount = self.rules.doubled_mines();
 
-                    if let Some(Some(val)) = grid
-                        .get_mut((new_pos_row as i32 - row_delta) as usize)
-                        .map(|row| row.get_mut((new_pos_col as i32 - col_delta) as usize))
-                    {
-                        *val = match val {
-                            Square::Empty => Square::Nearby(1),
-                            Square::Nearby(v) => Square::Nearby(*v + 1),
-                            Square::Mine => Square::Mine,
-                        }
-                    }
+        let mut mines = self.mined.ones().collect::<Vec<_>>();
+        let mut rng = SmallRng::seed_from_u64(self.seed.rotate_left(32));
+        let amount = amount.min(mines.len());
+        for i in 0..amount {
+            let j = rng.random_range(i..mines.len());
+            mines.swap(i, j);
+        }
+
+        self.doubled = BitSet::new(self.rows * self.cols);
+        for &i in &mines[..amount] {
+            self.doubled.set(i, true);
+        }
+    }
+
+    /// The true number of mines counted by `(row, col)`, before any lies
+    pub fn mines_around(&self, row: usize, col: usize) -> u32 {
+        self.counted_neighbours(row, col)
+            .map(|(r, c)| self.weight(r * self.cols + c))
+            .sum()
+    }
+
+    /// How much the square counts towards the numbers around it, 2 for a double mine. 0 for
+    /// squares not on the board.
+    pub fn mine_weight(&self, row: usize, col: usize) -> u32 {
+        if self.check_square(row, col).is_err() {
+            return 0;
+        }
+        self.weight(row * self.cols + col)
+    }
+
+    /// [`Minesweeper::mine_weight`] of the square at index `i` on the board
+    fn weight(&self, i: usize) -> u32 {
+        match (self.mined.get(i), self.doubled.get(i)) {
+            (true, true) => 2,
+            (true, false) => 1,
+            (false, _) => 0,
+        }
+    }
+
+    /// The neighbours a number on `(row, col)` counts, only the ones in the same row or column
+    /// with [`Rule::Orthogonal`]
+    pub fn counted_neighbours(
+        &self,
+        row: usize,
+        col: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let orthogonal = self.rules.counts_orthogonally();
+        self.neighbours(row, col)
+            .filter(move |&(r, c)| !orthogonal || r == row || c == col)
+    }
+
+    /// All neighbours of `(row, col)` in the board's topology
+    pub fn neighbours(&self, row: usize, col: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.topology.neighbours(self.rows, self.cols, row, col)
+    }
+
+    /// Changes which squares count as neighbours, and recounts the numbers unless they were
+    /// read from a visible board
+    pub fn with_topology(mut self, topology: Topology) -> Result<Self, MinesweeperError> {
+        if !topology.fits(self.rows, self.cols) {
+            return Err(MinesweeperError::TooSmallToWrap);
+        }
+
+        self.topology = topology;
+        if self.mines_placed && !self.numbers_as_written {
+            self.update_numbers();
+        }
+        Ok(self)
+    }
+
+    pub fn topology(&self) -> &Topology {
+        &self.topology
+    }
+
+    /// Adds puzzle rules to the board, and recounts the numbers unless they were read from a
+    /// visible board
+    pub fn with_rules(mut self, rules: Vec<Rule>) -> Self {
+        self.rules = rules;
+        if self.mines_placed && !self.numbers_as_written {
+            self.update_numbers();
+        }
+        self
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Changes how the mines are laid out. Mines that are already placed are laid out again,
+    /// around the same square on a deferred board. Fails if the strategy does not place every
+    /// mine on its own free square.
+    pub fn with_layout(
+        mut self,
+        layout: Arc<dyn LayoutStrategy>,
+    ) -> Result<Self, MinesweeperError> {
+        self.layout = layout;
+        if self.mines_placed {
+            self.mines_placed = false;
+            match self.laid_out_around {
+                Some((row, col)) => self.lay_out_mines(row, col)?,
+                None => self.place_mines(&*self.layout.clone(), |_, _| false)?,
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn layout(&self) -> &dyn LayoutStrategy {
+        &*self.layout
+    }
+
+    /// True when every number is exactly the mines around it, as on a classic board
+    pub fn has_exact_numbers(&self) -> bool {
+        self.rules.has_exact_numbers()
+    }
+
+    /// How many mines can be hit before the game is lost
+    pub fn lives(&self) -> u32 {
+        self.rules.lives()
+    }
+
+    /// How many opened squares are mines
+    pub fn mines_hit(&self) -> usize {
+        self.opened.count_and(&self.mined)
+    }
+
+    /// How many more flags can be placed with [`Rule::FlagCap`]
+    pub fn flags_left(&self) -> Option<usize> {
+        let cap = self.rules.flag_cap()?;
+        Some(cap.saturating_sub(self.flags()))
+    }
+
+    /// How many closed squares are flagged
+    pub fn flags(&self) -> usize {
+        self.marked.count() - self.marked.count_and(&self.opened)
+    }
+
+    /// Time since the first move, stopped at the last move once the game is over. Boards
+    /// without a history do not keep time.
+    pub fn elapsed(&self) -> Duration {
+        match (self.status, self.history.last()) {
+            (GameStatus::Won | GameStatus::Lost, Some(last)) => last.at,
+            _ => self
+                .started
+                .map_or(Duration::ZERO, |started| started.elapsed()),
+        }
+    }
+
+    /// The least number of clicks that opens every safe square without flags or chords, known
+    /// as the board's 3BV. Every opening takes one click, and so does every number that is not
+    /// next to an opening. Zero before the mines are placed.
+    pub fn three_bv(&self) -> usize {
+        if !self.mines_placed {
+            return 0;
+        }
+
+        let squares = self.rows * self.cols;
+        let mut reached = vec![false; squares];
+        let mut clicks = 0;
+
+        for start in (0..squares).filter(|&i| !self.mined.get(i) && self.counts[i] == 0) {
+            if reached[start] {
+                continue;
+            }
+            clicks += 1;
+
+            let mut stack = vec![start];
+            while let Some(i) = stack.pop() {
+                if std::mem::replace(&mut reached[i], true) || self.counts[i] != 0 {
+                    continue;
                 }
+                stack.extend(
+                    self.counted_neighbours(i / self.cols, i % self.cols)
+                        .map(|(n_row, n_col)| n_row * self.cols + n_col)
+                        .filter(|&n| !self.mined.get(n)),
+                );
             }
         }
-        Minesweeper {
-            grid,
-            opened: [[false; COLS]; ROWS],
-            marked: [[false; COLS]; ROWS],
+
+        clicks
+            + (0..squares)
+                .filter(|&i| !self.mined.get(i) && !reached[i])
+                .count()
+    }
+
+    pub fn stats(&self) -> GameStats {
+        GameStats {
+            time: self.elapsed(),
+            moves: self.history.len(),
+            three_bv: self.three_bv(),
+            opened: self.opened.count() - self.mines_hit(),
+            safe: self.rows * self.cols - self.mines,
+            flags: self.flags(),
+            correct_flags: self.marked.count_and(&self.mined),
         }
     }
 
-    /// Will return None if an already opend square is clicked
-    pub fn click(&mut self, row: usize, col: usize) -> Square {
-        // assert!(!self.opened[row][col]);
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
 
-        match self.grid[row][col] {
-            Square::Nearby(_) => {
-                self.opened[row][col] = true;
+    /// Total amount of mines on the board
+    pub fn mines(&self) -> usize {
+        self.mines
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The first click policy of a deferred board, None if mines were placed on creation
+    pub fn first_click(&self) -> Option<FirstClick> {
+        self.first_click
+    }
+
+    /// False until the first click on a deferred board
+    pub fn mines_placed(&self) -> bool {
+        self.mines_placed
+    }
+
+    /// True when the mines are laid out by [`layout`](Self::layout) from the seed, so the
+    /// same board can be made again from them. False for boards made from a list of mines or
+    /// read from text, until [`with_layout`](Self::with_layout) lays them out again.
+    pub fn mines_from_seed(&self) -> bool {
+        self.mines_from_seed
+    }
+
+    /// The square the mines of a deferred board were laid out around. This is usually the
+    /// first click, but not for boards made by [`Minesweeper::new_no_guess`].
+    pub fn laid_out_around(&self) -> Option<(usize, usize)> {
+        self.laid_out_around
+    }
+
+    /// Opens a square, flood filling from it if it is empty
+    pub fn click(&mut self, row: usize, col: usize) -> Result<MoveOutcome, MinesweeperError> {
+        self.check_square(row, col)?;
+        if self.status != GameStatus::Playing {
+            return Err(MinesweeperError::GameOver);
+        }
+        if self.is_square_open(row, col) {
+            return Err(MinesweeperError::AlreadyOpen);
+        }
+        if self.is_square_marked(row, col) {
+            return Err(MinesweeperError::Flagged);
+        }
+
+        self.lay_out_mines(row, col)?;
+
+        self.stack.push(row * self.cols + col);
+        let opened = self.flood();
+        Ok(self.finish_move(MoveKind::Open, row, col, opened))
+    }
+
+    /// Opens every unflagged neighbour counted by an opened number, once the flags and opened
+    /// mines around it count as much as the most the number can mean under the rules. A wrong
+    /// flag means a mine gets hit.
+    pub fn chord(&mut self, row: usize, col: usize) -> Result<MoveOutcome, MinesweeperError> {
+        self.check_square(row, col)?;
+        if self.status != GameStatus::Playing {
+            return Err(MinesweeperError::GameOver);
+        }
+
+        if !self.is_square_open(row, col) {
+            return Err(MinesweeperError::NotSatisfied);
+        }
+        let n = match self.shown(row, col) {
+            Square::Nearby(n) => n as usize,
+            // A liar's empty square still has a mine around it
+            Square::Empty if self.rules.has_lies() => 0,
+            Square::Empty | Square::Mine => return Err(MinesweeperError::NotSatisfied),
+        };
+
+        // Flags and opened mines count as much as they do in the number, and a wrong flag
+        // counts as one mine
+        let known = self
+            .counted_neighbours(row, col)
+            .map(|(n_row, n_col)| n_row * self.cols + n_col)
+            .map(|n| {
+                if self.opened.get(n) {
+                    self.weight(n) as usize
+                } else if self.marked.get(n) {
+                    self.weight(n).max(1) as usize
+                } else {
+                    0
+                }
+            })
+            .sum::<usize>();
+        if known != self.rules.most_counted(n) {
+            return Err(MinesweeperError::NotSatisfied);
+        }
+
+        let mut stack = std::mem::take(&mut self.stack);
+        stack.extend(
+            self.counted_neighbours(row, col)
+                .map(|(n_row, n_col)| n_row * self.cols + n_col)
+                .filter(|&n| !self.marked.get(n) && !self.opened.get(n)),
+        );
+        self.stack = stack;
+
+        let opened = self.flood();
+        if opened.is_empty() {
+            return Ok(MoveOutcome::Opened(opened));
+        }
+
+        Ok(self.finish_move(MoveKind::Chord, row, col, opened))
+    }
+
+    /// Opens the squares on the stack, flooding through empty squares. A mine is opened
+    /// without flooding from it. Returns where the squares it opened are in `squares`.
+    fn flood(&mut self) -> Range<usize> {
+        // Squares of a move that was not recorded are not needed any more
+        let start = self.recorded_end();
+        self.squares.truncate(start);
+        let mut stack = std::mem::take(&mut self.stack);
+
+        // Squares are opened as they are pushed, so none is pushed twice and the stack and
+        // `squares` never outgrow the board they were reserved for
+        stack.retain(|&i| !self.opened.get(i));
+        self.open_pushed(&stack, 0);
+
+        while let Some(i) = stack.pop() {
+            // The count before lies, a liar can show a number on a square without mines around
+            if self.mined.get(i) || self.counts[i] != 0 {
+                continue;
             }
-            Square::Empty => {
-                let mut stack = vec![(row, col)];
-                let neighbours = (-1..=1)
-                    .flat_map(|a| {
-                        (-1..=1)
-                            .filter_map(move |b| if a == 0 && b == 0 { None } else { Some((a, b)) })
-                    })
-                    .collect::<Vec<_>>();
 
-                while let Some((curr_row, curr_col)) = stack.pop() {
-                    if self.opened[curr_row][curr_col]
-                        || matches!(self.grid[curr_row][curr_col], Square::Mine)
-                    {
-                        continue;
-                    }
+            let pushed = stack.len();
+            let (row, col) = (i / self.cols, i % self.cols);
+            stack.extend(
+                self.counted_neighbours(row, col)
+                    .map(|(n_row, n_col)| n_row * self.cols + n_col)
+                    .filter(|&n| !self.mined.get(n) && !self.opened.get(n)),
+            );
+            self.open_pushed(&stack, pushed);
+        }
 
-                    self.opened[curr_row][curr_col] = true;
+        self.stack = stack;
+        start..self.squares.len()
+    }
 
-                    if matches!(self.grid[curr_row][curr_col], Square::Nearby(_)) {
-                        continue;
-                    }
+    /// Opens the squares pushed onto `stack` from `from` on
+    fn open_pushed(&mut self, stack: &[usize], from: usize) {
+        for &i in &stack[from..] {
+            self.opened.set(i, true);
+            self.squares.push((i / self.cols, i % self.cols));
+        }
+    }
 
-                    for (n_row, n_col) in &neighbours {
-                        let next_row = curr_row as i32 + n_row;
-                        let next_col = curr_col as i32 + n_col;
-                        if next_row < 0
-                            || next_row >= ROWS as i32
-                            || next_col < 0
-                            || next_col >= COLS as i32
-                        {
-                            continue;
-                        }
-
-                        stack.push((next_row as usize, next_col as usize));
-                    }
+    /// Where the squares of the recorded moves end in `squares`
+    fn recorded_end(&self) -> usize {
+        // The first undone move is the last one that was made
+        self.undone
+            .first()
+            .or(self.history.last())
+            .map_or(0, |last| last.opened.end)
+    }
+
+    /// The squares of a [`MoveOutcome::Opened`], including all squares reached by flood fill.
+    /// Without a history they are only kept until the next move.
+    pub fn opened_squares(&self, opened: Range<usize>) -> &[(usize, usize)] {
+        self.squares.get(opened).unwrap_or_default()
+    }
+
+    /// Every square a move of this board's history opened, including all squares reached by
+    /// flood fill
+    pub fn opened_by(&self, game_move: &Move) -> &[(usize, usize)] {
+        &self.squares[game_move.opened.clone()]
+    }
+
+    /// Records the move and updates the status after the squares in `opened` were opened
+    fn finish_move(
+        &mut self,
+        kind: MoveKind,
+        row: usize,
+        col: usize,
+        mut opened: Range<usize>,
+    ) -> MoveOutcome {
+        let hit_mine = self.squares[opened.clone()]
+            .iter()
+            .copied()
+            .find(|&(row, col)| self.mined.get(row * self.cols + col));
+        self.update_status(hit_mine.is_some());
+
+        if self.keep_history {
+            opened = self.record(kind, row, col, opened);
+        }
+        if !self.observers.0.is_empty() {
+            self.emit_opened(opened.clone());
+        }
+
+        match self.status {
+            GameStatus::Playing => match hit_mine {
+                Some((mine_row, mine_col)) => MoveOutcome::LostLife(mine_row, mine_col),
+                None => MoveOutcome::Opened(opened),
+            },
+            GameStatus::Lost => {
+                let (mine_row, mine_col) =
+                    hit_mine.expect("The game is only lost when a mine was opened");
+                MoveOutcome::HitMine(mine_row, mine_col)
+            }
+            GameStatus::Won => MoveOutcome::Won,
+        }
+    }
+
+    fn emit_opened(&mut self, opened: Range<usize>) {
+        self.emit(GameEvent::Flooded(self.squares[opened.clone()].to_vec()));
+        for i in opened {
+            let (row, col) = self.squares[i];
+            let square = self.shown(row, col);
+            self.emit(GameEvent::Opened { row, col, square });
+            if square == Square::Mine {
+                self.emit(GameEvent::MineHit { row, col });
+            }
+        }
+
+        match self.status {
+            GameStatus::Playing => {}
+            GameStatus::Won => self.emit(GameEvent::Won),
+            GameStatus::Lost => self.emit(GameEvent::Lost),
+        }
+    }
+
+    fn emit(&mut self, event: GameEvent) {
+        for observer in &mut self.observers.0 {
+            observer(&event);
+        }
+    }
+
+    /// Calls `observer` with every event from now on. Observers are not kept by clones or
+    /// restarts of the board.
+    pub fn subscribe(&mut self, observer: impl FnMut(&GameEvent) + Send + Sync + 'static) {
+        self.observers.0.push(Box::new(observer));
+    }
+
+    /// A channel receiving every event from now on, see [`Minesweeper::subscribe`]
+    pub fn events(&mut self) -> Receiver<GameEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribe(move |event| {
+            // The game goes on when nobody is listening any more
+            let _ = sender.send(event.clone());
+        });
+        receiver
+    }
+
+    /// Updates the status after a move, `hit_mine` is true when it opened a mine
+    fn update_status(&mut self, hit_mine: bool) {
+        if hit_mine && self.mines_hit() > self.lives() as usize {
+            self.status = GameStatus::Lost;
+        } else if self.is_board_completed() {
+            self.status = GameStatus::Won;
+        }
+    }
+
+    /// Adds the move to the history, forgetting the undone moves. Returns where the squares in
+    /// `opened` are after the squares of the undone moves are taken out.
+    fn record(
+        &mut self,
+        kind: MoveKind,
+        row: usize,
+        col: usize,
+        opened: Range<usize>,
+    ) -> Range<usize> {
+        let at = self.started.get_or_insert_with(Instant::now).elapsed();
+
+        let start = self.history.last().map_or(0, |last| last.opened.end);
+        self.squares.drain(start..opened.start);
+        let opened = start..start + opened.len();
+        self.undone.clear();
+
+        self.history.push(Move {
+            kind,
+            row,
+            col,
+            opened: opened.clone(),
+            at,
+        });
+        opened
+    }
+
+    /// Turns recording moves on or off. Without a history moves can not be undone or saved,
+    /// but simulations run faster.
+    pub fn with_history(mut self, keep_history: bool) -> Self {
+        self.keep_history = keep_history;
+        self
+    }
+
+    /// Takes back the last move. Mines stay where they are, even when the first click is undone.
+    pub fn undo(&mut self) -> Option<&Move> {
+        let last = self.history.pop()?;
+
+        match last.kind {
+            MoveKind::Open | MoveKind::Chord => {
+                for &(row, col) in &self.squares[last.opened.clone()] {
+                    self.opened.set(row * self.cols + col, false);
                 }
             }
-            Square::Mine => {
-                self.opened[row][col] = true;
+            MoveKind::Flag => self.marked.set(last.row * self.cols + last.col, false),
+            MoveKind::Unflag => self.marked.set(last.row * self.cols + last.col, true),
+        }
+        // Moves are only made while playing
+        self.status = GameStatus::Playing;
+
+        if !self.observers.0.is_empty() {
+            let (row, col) = (last.row, last.col);
+            self.emit(match last.kind {
+                MoveKind::Open | MoveKind::Chord => {
+                    GameEvent::Closed(self.squares[last.opened.clone()].to_vec())
+                }
+                MoveKind::Flag | MoveKind::Unflag => GameEvent::FlagToggled {
+                    row,
+                    col,
+                    flagged: last.kind == MoveKind::Unflag,
+                },
+            });
+        }
+
+        self.undone.push(last);
+        self.undone.last()
+    }
+
+    /// Makes the last undone move again
+    pub fn redo(&mut self) -> Option<&Move> {
+        let next = self.undone.pop()?;
+
+        match next.kind {
+            MoveKind::Open | MoveKind::Chord => {
+                let mut hit_mine = false;
+                for &(row, col) in &self.squares[next.opened.clone()] {
+                    self.opened.set(row * self.cols + col, true);
+                    hit_mine |= self.mined.get(row * self.cols + col);
+                }
+                self.update_status(hit_mine);
             }
+            MoveKind::Flag => self.marked.set(next.row * self.cols + next.col, true),
+            MoveKind::Unflag => self.marked.set(next.row * self.cols + next.col, false),
         }
-        self.grid[row][col]
+
+        if !self.observers.0.is_empty() {
+            match next.kind {
+                MoveKind::Open | MoveKind::Chord => self.emit_opened(next.opened.clone()),
+                MoveKind::Flag | MoveKind::Unflag => self.emit(GameEvent::FlagToggled {
+                    row: next.row,
+                    col: next.col,
+                    flagged: next.kind == MoveKind::Flag,
+                }),
+            }
+        }
+
+        self.history.push(next);
+        self.history.last()
+    }
+
+    /// Every move made so far, oldest first. Undone moves are not included.
+    pub fn history(&self) -> &[Move] {
+        &self.history
     }
 
-    pub fn square_state(&self, row: usize, col: usize) -> Square {
-        self.grid[row][col]
+    pub fn status(&self) -> GameStatus {
+        self.status
     }
 
+    /// The square as shown once it is opened, following the rules. None for squares not on the
+    /// board.
+    pub fn square_state(&self, row: usize, col: usize) -> Option<Square> {
+        self.check_square(row, col).ok()?;
+        Some(self.shown(row, col))
+    }
+
+    /// [`Minesweeper::square_state`] of a square that is on the board
+    pub(crate) fn shown(&self, row: usize, col: usize) -> Square {
+        let i = row * self.cols + col;
+        if self.mined.get(i) {
+            return Square::Mine;
+        }
+
+        let count = self.counts[i] as u32;
+        let shown = self.rules.shown_number(count, self.lies.get(i));
+
+        if shown == 0 {
+            Square::Empty
+        } else {
+            Square::Nearby(shown)
+        }
+    }
+
+    /// False for squares not on the board
+    pub fn is_mine(&self, row: usize, col: usize) -> bool {
+        self.check_square(row, col).is_ok() && self.mined.get(row * self.cols + col)
+    }
+
+    /// False for squares not on the board
     pub fn is_square_open(&self, row: usize, col: usize) -> bool {
-        self.opened[row][col]
+        self.check_square(row, col).is_ok() && self.opened.get(row * self.cols + col)
     }
 
+    /// False for squares not on the board
     pub fn is_square_marked(&self, row: usize, col: usize) -> bool {
-        self.marked[row][col]
+        self.check_square(row, col).is_ok() && self.marked.get(row * self.cols + col)
     }
 
-    pub fn mark(&mut self, row: usize, col: usize) {
-        if self.opened[row][col] {
-            return;
+    /// Flags or unflags a closed square
+    pub fn mark(&mut self, row: usize, col: usize) -> Result<MoveOutcome, MinesweeperError> {
+        self.check_square(row, col)?;
+        if self.status != GameStatus::Playing {
+            return Err(MinesweeperError::GameOver);
+        }
+        if self.is_square_open(row, col) {
+            return Err(MinesweeperError::AlreadyOpen);
+        }
+        if !self.is_square_marked(row, col) && self.flags_left() == Some(0) {
+            return Err(MinesweeperError::NoFlagsLeft);
+        }
+
+        let marked = !self.is_square_marked(row, col);
+        self.marked.set(row * self.cols + col, marked);
+
+        let (kind, outcome) = if marked {
+            (MoveKind::Flag, MoveOutcome::Flagged)
+        } else {
+            (MoveKind::Unflag, MoveOutcome::Unflagged)
+        };
+        if self.keep_history {
+            let end = self.recorded_end();
+            self.record(kind, row, col, end..end);
+        }
+        if !self.observers.0.is_empty() {
+            self.emit(GameEvent::FlagToggled {
+                row,
+                col,
+                flagged: marked,
+            });
         }
 
-        self.marked[row][col] = !self.marked[row][col];
+        Ok(outcome)
     }
 
-    /// Checks if all squares except the bombs are opened
+    /// Checks if all squares except the bombs are opened. Bombs opened while there were lives
+    /// left do not stop the board from being completed.
     pub fn is_board_completed(&self) -> bool {
-        // Crating a bitmap for each. if (res == 0) than won
+        // Every square has to be a bomb or opened
         // BOMB:   0 1 0 1
         // OPENED: 0 0 1 1
-        // RES:    1 0 0 1
-        // This is a XNOR gate or just equality of booleans;
-
-        let bomb_bit_map = self
-            .grid
-            .iter()
-            .flatten()
-            .map(|state| matches!(state, Square::Mine));
-
-        let opened_bit_map = self.opened.iter().flatten();
-
-        bomb_bit_map
-            .zip(opened_bit_map)
-            .map(|(a, b)| a == *b)
-            .all(|a| !a)
+        // RES:    0 1 1 1
+        // An OR gate, where the board is completed when all of them are 1
+        self.mined.covers_with(&self.opened)
     }
 
     // /// This is meant for a structrure to be able to train on
@@ -207,18 +1410,295 @@ impl Minesweeper {
     // }
 }
 
+/// A board needs squares, and at least one of them without a mine
+pub(crate) fn check_size(rows: usize, cols: usize, mines: usize) -> Result<(), MinesweeperError> {
+    if rows == 0 || cols == 0 {
+        return Err(MinesweeperError::EmptyBoard);
+    }
+    if mines >= rows * cols {
+        return Err(MinesweeperError::TooManyMines {
+            mines,
+            squares: rows * cols,
+        });
+    }
+    Ok(())
+}
+
+/// A game in the form it is serialized in. The board is written as text, see [`crate::text`]:
+/// the full board for the whole game, or the visible board for what a player sees, made by
+/// [`Minesweeper::visible`]. Everything but the board can be left out.
+///
+/// Opened numbers on a full board are checked against its mines and rules, like
+/// [`str::parse`] does, while a visible board keeps its numbers as written.
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SavedGame {
+    pub board: String,
+    /// Left out, it is the mines on a full board, or the flags and opened mines on a visible one
+    pub mines: Option<usize>,
+    pub seed: u64,
+    pub first_click: Option<FirstClick>,
+    pub laid_out_around: Option<(usize, usize)>,
+    pub topology: Topology,
+    pub rules: Vec<Rule>,
+    /// The name of the layout strategy, see [`layout::from_name`]. Strategies without a name
+    /// are left out and come back as [`Uniform`].
+    pub layout: Option<String>,
+    /// Left out, it is worked out from the board
+    pub status: Option<GameStatus>,
+    pub history: Vec<SavedMove>,
+    pub undone: Vec<SavedMove>,
+}
+
+/// A [`Move`] in the form it is serialized in, with the squares it opened
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SavedMove {
+    pub kind: MoveKind,
+    pub row: usize,
+    pub col: usize,
+    /// Every square the move opened, including all squares reached by flood fill
+    pub opened: Vec<(usize, usize)>,
+    /// Time since the first move of the game
+    pub at: Duration,
+}
+
+#[cfg(feature = "serde")]
+impl Minesweeper {
+    fn saved_move(&self, game_move: &Move) -> SavedMove {
+        SavedMove {
+            kind: game_move.kind,
+            row: game_move.row,
+            col: game_move.col,
+            opened: self.opened_by(game_move).to_vec(),
+            at: game_move.at,
+        }
+    }
+
+    /// Adds a move to the history or the undone moves, after the ones already there. The
+    /// history has to be loaded first, and undone moves from the last one to the first.
+    fn load_move(&mut self, saved: SavedMove) -> Result<Move, MinesweeperError> {
+        self.check_square(saved.row, saved.col)?;
+        for &(row, col) in &saved.opened {
+            self.check_square(row, col)?;
+        }
+
+        let start = self.squares.len();
+        self.squares.extend(saved.opened);
+        Ok(Move {
+            kind: saved.kind,
+            row: saved.row,
+            col: saved.col,
+            opened: start..self.squares.len(),
+            at: saved.at,
+        })
+    }
+
+    /// What a player sees of the game, without the mines under closed squares or the seed
+    /// they are laid out from
+    pub fn visible(&self) -> SavedGame {
+        SavedGame {
+            board: self.to_string(),
+            mines: Some(self.mines),
+            topology: self.topology.clone(),
+            rules: self.rules.clone(),
+            status: Some(self.status),
+            history: self
+                .history
+                .iter()
+                .map(|game_move| self.saved_move(game_move))
+                .collect(),
+            ..SavedGame::default()
+        }
+    }
+
+    /// Reads a full board, counting the numbers again from the mines and checking the opened
+    /// ones
+    fn from_saved_board(rows: &[Vec<char>], saved: &SavedGame) -> Result<Self, MinesweeperError> {
+        let cols = rows[0].len();
+        let mut game = Self::empty(rows.len(), cols, 0, saved.seed, saved.first_click);
+        game.topology = saved.topology.clone();
+        game.rules = saved.rules.clone();
+
+        let mut shown = Vec::new();
+        for (row, symbols) in rows.iter().enumerate() {
+            for (col, &symbol) in symbols.iter().enumerate() {
+                let i = row * cols + col;
+                let parsed =
+                    parse_symbol(symbol).ok_or(TextError::UnknownSymbol { row, col, symbol })?;
+                game.mined.set(i, parsed.is_mine);
+                game.opened.set(i, parsed.opened);
+                game.marked.set(i, parsed.marked);
+                match parsed.square {
+                    Some(Square::Empty) => shown.push((row, col, 0)),
+                    Some(Square::Nearby(n)) => shown.push((row, col, n)),
+                    _ => {}
+                }
+            }
+        }
+
+        let on_board = game.mined.count();
+        game.mines = saved.mines.unwrap_or(on_board);
+        check_size(game.rows, game.cols, game.mines)?;
+        // Only a deferred board before its first click may have none of its mines yet
+        let deferred = game.first_click.is_some() && on_board == 0;
+        if on_board != game.mines && !deferred {
+            return Err(MinesweeperError::WrongMineCount {
+                mines: game.mines,
+                on_board,
+            });
+        }
+        if let Some((row, col)) = saved.laid_out_around {
+            game.check_square(row, col)?;
+            game.laid_out_around = Some((row, col));
+        }
+        if let Some(name) = &saved.layout {
+            game.layout = layout::from_name(name)
+                .map_err(|_| MinesweeperError::UnknownLayout(name.clone()))?;
+        }
+
+        game.mines_placed = !deferred || game.mines == 0;
+        game.mines_from_seed = !game.mines_placed;
+        if game.mines_placed {
+            game.update_numbers();
+        }
+
+        for (row, col, shown) in shown {
+            let mines = match game.shown(row, col) {
+                Square::Empty => 0,
+                Square::Nearby(n) => n,
+                Square::Mine => continue,
+            };
+            if shown != mines {
+                return Err(TextError::WrongNumber {
+                    row,
+                    col,
+                    shown,
+                    mines,
+                }
+                .into());
+            }
+        }
+
+        Ok(game)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Minesweeper> for SavedGame {
+    fn from(game: Minesweeper) -> Self {
+        // A board read from visible text only knows its numbers as written
+        let board = if game.numbers_as_written {
+            game.to_string()
+        } else {
+            format!("{game:#}")
+        };
+        SavedGame {
+            board,
+            mines: Some(game.mines),
+            seed: game.seed,
+            first_click: game.first_click,
+            laid_out_around: game.laid_out_around,
+            layout: game.layout.name(),
+            status: Some(game.status),
+            history: game
+                .history
+                .iter()
+                .map(|game_move| game.saved_move(game_move))
+                .collect(),
+            undone: game
+                .undone
+                .iter()
+                .map(|game_move| game.saved_move(game_move))
+                .collect(),
+            topology: game.topology,
+            rules: game.rules,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SavedGame> for Minesweeper {
+    type Error = MinesweeperError;
+
+    fn try_from(saved: SavedGame) -> Result<Self, Self::Error> {
+        let rows = split_rows(&saved.board)?;
+        if !saved.topology.fits(rows.len(), rows[0].len()) {
+            return Err(MinesweeperError::TooSmallToWrap);
+        }
+
+        let mut game = if rows.iter().flatten().any(|&symbol| symbol == '░') {
+            let mines = saved.mines.unwrap_or_else(|| {
+                rows.iter()
+                    .flatten()
+                    .filter(|&&symbol| matches!(symbol, 'F' | '*'))
+                    .count()
+            });
+            let mut game = parse_visible(&rows, mines, saved.topology.clone())?;
+            // The numbers are kept as written, so rules that change numbers are left out
+            game.rules = saved
+                .rules
+                .iter()
+                .filter(|rule| !rule.changes_numbers())
+                .cloned()
+                .collect();
+            game
+        } else {
+            Self::from_saved_board(&rows, &saved)?
+        };
+
+        game.history = saved
+            .history
+            .into_iter()
+            .map(|saved_move| game.load_move(saved_move))
+            .collect::<Result<_, _>>()?;
+        let mut undone = saved
+            .undone
+            .into_iter()
+            .rev()
+            .map(|saved_move| game.load_move(saved_move))
+            .collect::<Result<Vec<_>, _>>()?;
+        undone.reverse();
+        game.undone = undone;
+        // Keeps the move times going from where they were
+        game.started = game
+            .history
+            .last()
+            .and_then(|last| Instant::now().checked_sub(last.at));
+
+        match saved.status {
+            Some(status) => game.status = status,
+            None => game.update_status(game.mines_hit() > 0),
+        }
+
+        Ok(game)
+    }
+}
+
+/// Prints the visible board, or the full board including mines with `{:#}`
 impl Display for Minesweeper {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut s = String::new();
-        for row in 0..ROWS {
-            for col in 0..COLS {
-                if self.opened[row][col] {
-                    s.push(match self.grid[row][col] {
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                if self.is_square_open(row, col) {
+                    s.push(match self.shown(row, col) {
                         Square::Empty => ' ',
-                        Square::Nearby(n) => char::from_digit(n, 10).unwrap_or('?'),
+                        Square::Nearby(n) => number_symbol(n),
                         Square::Mine => '*',
                     });
-                } else if self.marked[row][col] {
+                } else if f.alternate() {
+                    // The full board, see `crate::text`
+                    s.push(
+                        match (self.is_square_marked(row, col), self.is_mine(row, col)) {
+                            (false, false) => '.',
+                            (false, true) => 'x',
+                            (true, false) => 'f',
+                            (true, true) => 'F',
+                        },
+                    );
+                } else if self.is_square_marked(row, col) {
                     s.push('F'); // Flagged
                 } else {
                     s.push('░'); // Closed square
@@ -229,3 +1709,353 @@ impl Display for Minesweeper {
         write!(f, "{}", s)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flags the mines around every opened number, leaving out one of the counted ones when
+    /// `leave_one` is true, and chords it. With only right flags no mine may ever be opened.
+    fn chord_with_right_flags(rules: &[Rule], leave_one: bool) -> usize {
+        let mut chorded = 0;
+        for seed in 0..40 {
+            let mut game = Minesweeper::new_deferred_seeded(9, 9, 20, FirstClick::Opening, seed)
+                .unwrap()
+                .with_rules(rules.to_vec());
+            game.click(4, 4).unwrap();
+
+            for (row, col) in game.opened.ones().map(|i| (i / 9, i % 9)) {
+                let mut game = game.clone();
+                let mut left_out = !leave_one;
+                let counted = game.counted_neighbours(row, col).collect::<Vec<_>>();
+                for (n_row, n_col) in game.neighbours(row, col).collect::<Vec<_>>() {
+                    if !game.is_mine(n_row, n_col) || game.is_square_open(n_row, n_col) {
+                        continue;
+                    }
+                    if !left_out && counted.contains(&(n_row, n_col)) {
+                        left_out = true;
+                        continue;
+                    }
+                    game.mark(n_row, n_col).unwrap();
+                }
+
+                if let Ok(outcome) = game.chord(row, col) {
+                    assert!(
+                        matches!(outcome, MoveOutcome::Opened(_) | MoveOutcome::Won),
+                        "{outcome:?} chording ({row}, {col}) with {rules:?}\n{game:#}"
+                    );
+                    chorded += 1;
+                }
+            }
+        }
+        chorded
+    }
+
+    #[test]
+    fn chord_never_opens_a_mine_with_right_flags() {
+        for rules in [
+            vec![],
+            vec![Rule::Liar],
+            vec![Rule::DoubleMines(5)],
+            vec![Rule::Orthogonal],
+            vec![Rule::Liar, Rule::Orthogonal, Rule::DoubleMines(3)],
+        ] {
+            assert!(chord_with_right_flags(&rules, false) > 0, "{rules:?}");
+            chord_with_right_flags(&rules, true);
+        }
+    }
+
+    #[test]
+    fn chord_counts_double_mines_twice() {
+        let mut layout = vec![vec![false; 3]; 3];
+        layout[0][0] = true;
+        layout[0][1] = true;
+        let mut game = Minesweeper::from_mine_layout(&layout)
+            .unwrap()
+            .with_rules(vec![Rule::DoubleMines(2)]);
+        game.click(1, 1).unwrap();
+        assert_eq!(game.square_state(1, 1), Some(Square::Nearby(4)));
+
+        game.mark(0, 0).unwrap();
+        assert_eq!(game.chord(1, 1), Err(MinesweeperError::NotSatisfied));
+        game.mark(0, 1).unwrap();
+        assert_eq!(game.chord(1, 1), Ok(MoveOutcome::Won));
+    }
+
+    #[test]
+    fn history_keeps_the_opened_squares_of_every_move() {
+        let mut game = Minesweeper::new_seeded(9, 9, 10, 3).unwrap();
+        let mut boards = vec![game.to_string()];
+        for i in 0..81 {
+            let (row, col) = (i / 9, i % 9);
+            if game.is_mine(row, col) || game.is_square_open(row, col) {
+                continue;
+            }
+            let Ok(MoveOutcome::Opened(opened)) = game.click(row, col) else {
+                continue;
+            };
+            assert_eq!(
+                game.opened_by(game.history().last().unwrap()),
+                game.opened_squares(opened)
+            );
+            boards.push(game.to_string());
+            if boards.len() == 5 {
+                break;
+            }
+        }
+        assert_eq!(boards.len(), 5);
+
+        for board in boards.iter().rev().skip(1) {
+            game.undo().unwrap();
+            assert_eq!(&game.to_string(), board);
+        }
+        for board in &boards[1..3] {
+            game.redo().unwrap();
+            assert_eq!(&game.to_string(), board);
+        }
+
+        // A new move forgets the undone ones and their squares
+        let closed = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(row, col)| !game.is_square_open(row, col))
+            .unwrap();
+        game.mark(closed.0, closed.1).unwrap();
+        assert!(game.redo().is_none());
+        assert_eq!(game.history().len(), 3);
+        let opened = game
+            .history()
+            .iter()
+            .map(|game_move| game.opened_by(game_move).len())
+            .sum::<usize>();
+        assert_eq!(opened, game.squares.len());
+        assert_eq!(opened, game.opened.count());
+    }
+
+    #[test]
+    fn restart_keeps_history_off_and_flags_agree() {
+        let mut game = Minesweeper::new_seeded(9, 9, 10, 3)
+            .unwrap()
+            .with_history(false)
+            .with_rules(vec![Rule::FlagCap(3)]);
+        let restarted = game.restart(4);
+        assert!(!restarted.keep_history);
+
+        let safe = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(row, col)| !game.is_mine(row, col))
+            .unwrap();
+        game.mark(safe.0, safe.1).unwrap();
+        assert_eq!(game.flags_left(), Some(2));
+        assert!(game.history().is_empty());
+
+        // Flood fill opens flagged squares, which are no longer counted as flags
+        game.opened.set(safe.0 * 9 + safe.1, true);
+        assert_eq!(game.flags(), 0);
+        assert_eq!(game.flags_left(), Some(3));
+    }
+
+    #[test]
+    fn orthogonal_chord_ignores_diagonal_flags() {
+        // The 1 in the middle only counts the mine above it
+        let mut game = Minesweeper::from_mine_layout(&[
+            vec![true, true, false],
+            vec![false, false, false],
+            vec![false, false, false],
+        ])
+        .unwrap()
+        .with_rules(vec![Rule::Orthogonal]);
+        game.click(1, 1).unwrap();
+        assert_eq!(game.square_state(1, 1), Some(Square::Nearby(1)));
+
+        game.mark(0, 0).unwrap();
+        assert_eq!(game.chord(1, 1), Err(MinesweeperError::NotSatisfied));
+
+        // Every safe square is reached from the three counted ones
+        game.mark(0, 1).unwrap();
+        assert_eq!(game.chord(1, 1), Ok(MoveOutcome::Won));
+    }
+
+    /// Leaves one mine out
+    #[derive(Debug)]
+    struct OneShort;
+
+    impl LayoutStrategy for OneShort {
+        fn lay_out(
+            &self,
+            rows: usize,
+            cols: usize,
+            mines: usize,
+            excluded: &dyn Fn(usize, usize) -> bool,
+            rng: &mut SmallRng,
+        ) -> Vec<(usize, usize)> {
+            Uniform.lay_out(rows, cols, mines - 1, excluded, rng)
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_send_events() {
+        let mut game = Minesweeper::new_seeded(9, 9, 10, 3).unwrap();
+        let events = game.events();
+        let safe = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(row, col)| !game.is_mine(row, col))
+            .unwrap();
+
+        game.click(safe.0, safe.1).unwrap();
+        let clicked = events.try_iter().collect::<Vec<_>>();
+        let Some(GameEvent::Flooded(opened)) = clicked.first() else {
+            panic!("{clicked:?}");
+        };
+        game.undo().unwrap();
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [GameEvent::Closed(opened.clone())]
+        );
+        game.redo().unwrap();
+        assert_eq!(events.try_iter().collect::<Vec<_>>(), clicked);
+
+        let closed = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(row, col)| !game.is_square_open(row, col))
+            .unwrap();
+        game.mark(closed.0, closed.1).unwrap();
+        game.undo().unwrap();
+        game.redo().unwrap();
+        let flagged = |flagged| GameEvent::FlagToggled {
+            row: closed.0,
+            col: closed.1,
+            flagged,
+        };
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [flagged(true), flagged(false), flagged(true)]
+        );
+    }
+
+    #[test]
+    fn squares_off_the_board_are_errors() {
+        let mut layout = vec![vec![true; 3]; 2];
+        layout[1][1] = false;
+        let mut game = Minesweeper::from_mine_layout(&layout).unwrap();
+        game.mark(0, 0).unwrap();
+
+        let off_board = MinesweeperError::OutOfBounds { row: 0, col: 3 };
+        assert_eq!(game.click(0, 3), Err(off_board.clone()));
+        assert_eq!(game.mark(0, 3), Err(off_board.clone()));
+        assert_eq!(game.chord(0, 3), Err(off_board));
+        // The squares past the end of a row are the first ones of the next row inside the board
+        assert_eq!(game.square_state(0, 3), None);
+        assert!(!game.is_mine(0, 3));
+        assert!(!game.is_square_marked(2, 0));
+        assert!(!game.is_square_open(0, 4));
+        assert_eq!(game.mine_weight(0, 3), 0);
+        assert_eq!(game.square_state(1, 0), Some(Square::Mine));
+    }
+
+    #[test]
+    fn layouts_that_miss_a_mine_are_errors() {
+        let game = Minesweeper::new_seeded(9, 9, 10, 1).unwrap();
+        assert_eq!(
+            game.with_layout(Arc::new(OneShort)).unwrap_err(),
+            MinesweeperError::BadLayout("OneShort".to_string())
+        );
+
+        let mut deferred = Minesweeper::new_deferred_seeded(9, 9, 10, FirstClick::Safe, 1)
+            .unwrap()
+            .with_layout(Arc::new(OneShort))
+            .unwrap();
+        assert!(matches!(
+            deferred.click(4, 4),
+            Err(MinesweeperError::BadLayout(_))
+        ));
+        assert!(!deferred.mines_placed());
+        assert_eq!(deferred.laid_out_around(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn saved_games_read_back() {
+        let boards = [
+            (FirstClick::Opening, "square", "", "uniform"),
+            (
+                FirstClick::Safe,
+                "hex+wrap",
+                "liar,lives:2",
+                "clustered:3:2",
+            ),
+            (
+                FirstClick::MoveToCorner,
+                "knight",
+                "double:3,flags:5",
+                "relocating:uniform",
+            ),
+        ];
+
+        for (first_click, topology, rules, layout) in boards {
+            for seed in 0..10 {
+                let rules = rules
+                    .split(',')
+                    .filter(|rule| !rule.is_empty())
+                    .map(|rule| rule.parse().unwrap())
+                    .collect();
+                let mut game = Minesweeper::new_deferred_seeded(8, 10, 15, first_click, seed)
+                    .unwrap()
+                    .with_layout(layout::from_name(layout).unwrap())
+                    .unwrap()
+                    .with_topology(topology.parse().unwrap())
+                    .unwrap()
+                    .with_rules(rules);
+
+                let mut rng = SmallRng::seed_from_u64(seed);
+                for _ in 0..40 {
+                    let (row, col) = (rng.random_range(0..8), rng.random_range(0..10));
+                    let _ = match rng.random_range(0..10) {
+                        0..6 => game.click(row, col),
+                        6..8 => game.mark(row, col),
+                        _ => game.chord(row, col),
+                    };
+                }
+                game.undo();
+                game.undo();
+
+                let saved = SavedGame::from(game.clone());
+                let mut read = Minesweeper::try_from(saved.clone()).unwrap();
+                assert_eq!(
+                    SavedGame::from(read.clone()),
+                    saved,
+                    "{topology}, seed {seed}"
+                );
+                assert_eq!(read.laid_out_around(), game.laid_out_around());
+
+                // Undone moves can still be made again
+                game.redo();
+                read.redo();
+                assert_eq!(format!("{read:#}"), format!("{game:#}"));
+                assert_eq!(read.status(), game.status());
+
+                let visible = Minesweeper::try_from(game.visible()).unwrap();
+                assert_eq!(visible.to_string(), game.to_string());
+                assert_eq!(visible.status(), game.status());
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn saved_games_need_the_mines_on_their_board() {
+        let game = Minesweeper::new_seeded(9, 9, 10, 1).unwrap();
+        let mut saved = SavedGame::from(game);
+        saved.mines = Some(11);
+        assert_eq!(
+            Minesweeper::try_from(saved.clone()).unwrap_err(),
+            MinesweeperError::WrongMineCount {
+                mines: 11,
+                on_board: 10
+            }
+        );
+
+        // Deferred boards before their first click have no mines on them yet
+        let deferred = Minesweeper::new_deferred_seeded(9, 9, 10, FirstClick::Safe, 1).unwrap();
+        assert!(Minesweeper::try_from(SavedGame::from(deferred)).is_ok());
+    }
+}
@@ -0,0 +1,441 @@
+//! Minesweeper in three dimensions.
+//!
+//! The board is a stack of `layers`, each one a `rows` x `cols` grid. Within its layer and the
+//! layers above and below, a square touches the squares its [`Topology`] says it does, and it
+//! also touches the same square in those two layers. With the classic topology these are the
+//! up to 26 squares around it in the cube. Squares are addressed as `(layer, row, col)`.
+//! Mines are laid out on the first click with [`Uniform`], and the first click always opens up
+//! an area like [`FirstClick::Opening`](crate::game::FirstClick::Opening) does on flat boards.
+
+use rand::{rngs::SmallRng, SeedableRng};
+
+use crate::{
+    game::{check_size, GameStatus, MinesweeperError, Square},
+    layout::{LayoutStrategy, Uniform},
+    topology::Topology,
+};
+
+/// What happened when a move was made on a 3D board
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MoveOutcome3d {
+    /// Every square the move opened, including all squares reached by flood fill
+    Opened(Vec<(usize, usize, usize)>),
+    HitMine(usize, usize, usize),
+    Won,
+    Flagged,
+    Unflagged,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Minesweeper3d {
+    grid: Vec<Vec<Vec<Square>>>,
+    opened: Vec<Vec<Vec<bool>>>,
+    marked: Vec<Vec<Vec<bool>>>,
+    topology: Topology,
+    layers: usize,
+    rows: usize,
+    cols: usize,
+    mines: usize,
+    seed: u64,
+    mines_placed: bool,
+    status: GameStatus,
+}
+
+impl Minesweeper3d {
+    pub fn new(
+        layers: usize,
+        rows: usize,
+        cols: usize,
+        mines: usize,
+    ) -> Result<Self, MinesweeperError> {
+        Self::new_seeded(layers, rows, cols, mines, rand::random::<u64>())
+    }
+
+    /// Same seed and same first click will always give the same board
+    pub fn new_seeded(
+        layers: usize,
+        rows: usize,
+        cols: usize,
+        mines: usize,
+        seed: u64,
+    ) -> Result<Self, MinesweeperError> {
+        check_size(layers * rows, cols, mines)?;
+
+        Ok(Minesweeper3d {
+            grid: vec![vec![vec![Square::Empty; cols]; rows]; layers],
+            opened: vec![vec![vec![false; cols]; rows]; layers],
+            marked: vec![vec![vec![false; cols]; rows]; layers],
+            topology: Topology::default(),
+            layers,
+            rows,
+            cols,
+            mines,
+            seed,
+            mines_placed: false,
+            status: GameStatus::Playing,
+        })
+    }
+
+    /// Creates a board with mines where `layout[layer][row][col]` is true. The uneven row of
+    /// [`MinesweeperError::UnevenLayout`] is counted through all layers, row `row` of layer
+    /// `layer` is `layer * rows + row`.
+    pub fn from_mine_layout(layout: &[Vec<Vec<bool>>]) -> Result<Self, MinesweeperError> {
+        let layers = layout.len();
+        let rows = layout.first().map_or(0, Vec::len);
+        let cols = layout
+            .first()
+            .and_then(|layer| layer.first())
+            .map_or(0, Vec::len);
+        if let Some(layer) = layout
+            .iter()
+            .position(|layout_layer| layout_layer.len() != rows)
+        {
+            return Err(MinesweeperError::UnevenLayout(layer * rows));
+        }
+        if let Some(row) = layout
+            .iter()
+            .flatten()
+            .position(|layout_row| layout_row.len() != cols)
+        {
+            return Err(MinesweeperError::UnevenLayout(row));
+        }
+        let mines = layout
+            .iter()
+            .flatten()
+            .flatten()
+            .filter(|&&mine| mine)
+            .count();
+
+        let mut minesweeper = Self::new_seeded(layers, rows, cols, mines, 0)?;
+        for (layer, layout_layer) in layout.iter().enumerate() {
+            for (row, layout_row) in layout_layer.iter().enumerate() {
+                for (col, &mine) in layout_row.iter().enumerate() {
+                    if mine {
+                        minesweeper.grid[layer][row][col] = Square::Mine;
+                    }
+                }
+            }
+        }
+        minesweeper.update_numbers();
+        minesweeper.mines_placed = true;
+
+        Ok(minesweeper)
+    }
+
+    /// Lays out the mines as if `(layer, row, col)` was the first click, keeping it and its
+    /// neighbours free. Does nothing once the mines are placed.
+    pub fn lay_out_mines(
+        &mut self,
+        layer: usize,
+        row: usize,
+        col: usize,
+    ) -> Result<(), MinesweeperError> {
+        self.check_square(layer, row, col)?;
+        if self.mines_placed {
+            return Ok(());
+        }
+
+        let mut opening = vec![false; self.layers * self.rows * self.cols];
+        let index =
+            |layer: usize, row: usize, col: usize| (layer * self.rows + row) * self.cols + col;
+        opening[index(layer, row, col)] = true;
+        for (l, r, c) in self.neighbours(layer, row, col) {
+            opening[index(l, r, c)] = true;
+        }
+        // Small boards can not fit the mines around a whole opening, only the square itself
+        if opening.len() - opening.iter().filter(|&&excluded| excluded).count() < self.mines {
+            opening.fill(false);
+            opening[index(layer, row, col)] = true;
+        }
+
+        // The layers are laid out as one flat board, with each layer's rows after the last one's
+        let mut rng = SmallRng::seed_from_u64(self.seed);
+        let excluded = |row: usize, col: usize| opening[row * self.cols + col];
+        for (row, col) in Uniform.lay_out(
+            self.layers * self.rows,
+            self.cols,
+            self.mines,
+            &excluded,
+            &mut rng,
+        ) {
+            self.grid[row / self.rows][row % self.rows][col] = Square::Mine;
+        }
+
+        self.update_numbers();
+        self.mines_placed = true;
+        Ok(())
+    }
+
+    fn check_square(&self, layer: usize, row: usize, col: usize) -> Result<(), MinesweeperError> {
+        if layer >= self.layers || row >= self.rows || col >= self.cols {
+            return Err(MinesweeperError::OutOfBounds3d { layer, row, col });
+        }
+        Ok(())
+    }
+
+    /// Recalculates the number on every square that is not a mine
+    fn update_numbers(&mut self) {
+        for layer in 0..self.layers {
+            for row in 0..self.rows {
+                for col in 0..self.cols {
+                    if matches!(self.grid[layer][row][col], Square::Mine) {
+                        continue;
+                    }
+
+                    let nearby = self
+                        .neighbours(layer, row, col)
+                        .filter(|&(l, r, c)| matches!(self.grid[l][r][c], Square::Mine))
+                        .count() as u32;
+
+                    self.grid[layer][row][col] = if nearby == 0 {
+                        Square::Empty
+                    } else {
+                        Square::Nearby(nearby)
+                    };
+                }
+            }
+        }
+    }
+
+    /// The neighbours of `(row, col)` in its own layer and the layers next to it, and the same
+    /// square in the layers next to it. Up to 26 squares with the classic topology.
+    pub fn neighbours(
+        &self,
+        layer: usize,
+        row: usize,
+        col: usize,
+    ) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        (layer.saturating_sub(1)..self.layers.min(layer + 2)).flat_map(move |l| {
+            let same_square = (l != layer).then_some((l, row, col));
+            self.topology
+                .neighbours(self.rows, self.cols, row, col)
+                .map(move |(r, c)| (l, r, c))
+                .chain(same_square)
+        })
+    }
+
+    /// Uses another topology within the layers, see [`Topology`]. Fails with
+    /// [`MinesweeperError::TooSmallToWrap`] like flat boards do.
+    pub fn with_topology(mut self, topology: Topology) -> Result<Self, MinesweeperError> {
+        if !topology.fits(self.rows, self.cols) {
+            return Err(MinesweeperError::TooSmallToWrap);
+        }
+
+        self.topology = topology;
+        if self.mines_placed {
+            self.update_numbers();
+        }
+        Ok(self)
+    }
+
+    pub fn topology(&self) -> &Topology {
+        &self.topology
+    }
+
+    pub fn layers(&self) -> usize {
+        self.layers
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn mines(&self) -> usize {
+        self.mines
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn mines_placed(&self) -> bool {
+        self.mines_placed
+    }
+
+    pub fn status(&self) -> GameStatus {
+        self.status
+    }
+
+    /// Opens a square, flood filling from it if it is empty
+    pub fn click(
+        &mut self,
+        layer: usize,
+        row: usize,
+        col: usize,
+    ) -> Result<MoveOutcome3d, MinesweeperError> {
+        self.check_square(layer, row, col)?;
+        if self.status != GameStatus::Playing {
+            return Err(MinesweeperError::GameOver);
+        }
+        if self.opened[layer][row][col] {
+            return Err(MinesweeperError::AlreadyOpen);
+        }
+        if self.marked[layer][row][col] {
+            return Err(MinesweeperError::Flagged);
+        }
+
+        self.lay_out_mines(layer, row, col)?;
+
+        let opened = self.open(layer, row, col);
+        if matches!(self.grid[layer][row][col], Square::Mine) {
+            self.status = GameStatus::Lost;
+            return Ok(MoveOutcome3d::HitMine(layer, row, col));
+        }
+        if self.is_board_completed() {
+            self.status = GameStatus::Won;
+            return Ok(MoveOutcome3d::Won);
+        }
+
+        Ok(MoveOutcome3d::Opened(opened))
+    }
+
+    /// Opens the square and floods through empty squares. Returns every square it opened.
+    fn open(&mut self, layer: usize, row: usize, col: usize) -> Vec<(usize, usize, usize)> {
+        let mut opened = Vec::new();
+        let mut stack = vec![(layer, row, col)];
+
+        while let Some((l, r, c)) = stack.pop() {
+            if self.opened[l][r][c] || self.marked[l][r][c] {
+                continue;
+            }
+
+            self.opened[l][r][c] = true;
+            opened.push((l, r, c));
+
+            if matches!(self.grid[l][r][c], Square::Empty) {
+                stack.extend(self.neighbours(l, r, c));
+            }
+        }
+
+        opened
+    }
+
+    /// Flags or unflags a closed square
+    pub fn mark(
+        &mut self,
+        layer: usize,
+        row: usize,
+        col: usize,
+    ) -> Result<MoveOutcome3d, MinesweeperError> {
+        self.check_square(layer, row, col)?;
+        if self.status != GameStatus::Playing {
+            return Err(MinesweeperError::GameOver);
+        }
+        if self.opened[layer][row][col] {
+            return Err(MinesweeperError::AlreadyOpen);
+        }
+
+        self.marked[layer][row][col] = !self.marked[layer][row][col];
+
+        if self.marked[layer][row][col] {
+            Ok(MoveOutcome3d::Flagged)
+        } else {
+            Ok(MoveOutcome3d::Unflagged)
+        }
+    }
+
+    /// None for squares not on the board
+    pub fn square_state(&self, layer: usize, row: usize, col: usize) -> Option<Square> {
+        self.grid.get(layer)?.get(row)?.get(col).copied()
+    }
+
+    /// False for squares not on the board
+    pub fn is_mine(&self, layer: usize, row: usize, col: usize) -> bool {
+        self.square_state(layer, row, col) == Some(Square::Mine)
+    }
+
+    /// False for squares not on the board
+    pub fn is_square_open(&self, layer: usize, row: usize, col: usize) -> bool {
+        self.opened
+            .get(layer)
+            .and_then(|opened| opened.get(row)?.get(col))
+            .is_some_and(|&opened| opened)
+    }
+
+    /// False for squares not on the board
+    pub fn is_square_marked(&self, layer: usize, row: usize, col: usize) -> bool {
+        self.marked
+            .get(layer)
+            .and_then(|marked| marked.get(row)?.get(col))
+            .is_some_and(|&marked| marked)
+    }
+
+    /// Checks if all squares except the mines are opened
+    pub fn is_board_completed(&self) -> bool {
+        self.grid
+            .iter()
+            .flatten()
+            .flatten()
+            .zip(self.opened.iter().flatten().flatten())
+            .all(|(square, &opened)| matches!(square, Square::Mine) != opened)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn squares_off_the_board_are_errors() {
+        assert_eq!(
+            Minesweeper3d::new_seeded(2, 2, 2, 8, 0).unwrap_err(),
+            MinesweeperError::TooManyMines {
+                mines: 8,
+                squares: 8
+            }
+        );
+        assert_eq!(
+            Minesweeper3d::from_mine_layout(&[vec![vec![false; 2]; 2], vec![vec![false; 2]]])
+                .unwrap_err(),
+            MinesweeperError::UnevenLayout(2)
+        );
+
+        let mut game = Minesweeper3d::new_seeded(2, 3, 4, 5, 0).unwrap();
+        let off_board = MinesweeperError::OutOfBounds3d {
+            layer: 2,
+            row: 0,
+            col: 0,
+        };
+        assert_eq!(game.click(2, 0, 0), Err(off_board.clone()));
+        assert_eq!(game.mark(2, 0, 0), Err(off_board));
+        assert!(!game.mines_placed());
+        assert_eq!(game.square_state(0, 3, 0), None);
+        assert!(!game.is_square_open(0, 0, 4));
+        assert!(!game.is_square_marked(0, 0, 4));
+
+        game.click(0, 0, 0).unwrap();
+        assert_eq!(game.click(0, 0, 0), Err(MinesweeperError::AlreadyOpen));
+    }
+
+    #[test]
+    fn neighbours_follow_the_topology() {
+        let game = Minesweeper3d::new_seeded(3, 3, 3, 1, 0).unwrap();
+        assert_eq!(game.neighbours(1, 1, 1).count(), 26);
+        assert_eq!(game.neighbours(0, 0, 0).count(), 7);
+
+        let game = game.with_topology("cross".parse().unwrap()).unwrap();
+        assert_eq!(game.neighbours(1, 1, 1).count(), 14);
+        assert_eq!(game.neighbours(0, 0, 0).count(), 5);
+    }
+
+    #[test]
+    fn full_boards_lay_out_every_mine() {
+        for seed in 0..10 {
+            let mut game = Minesweeper3d::new_seeded(2, 3, 3, 17, seed).unwrap();
+            assert_eq!(game.click(1, 2, 2), Ok(MoveOutcome3d::Won));
+            let mines = (0..2)
+                .flat_map(|layer| {
+                    (0..3).flat_map(move |row| (0..3).map(move |col| (layer, row, col)))
+                })
+                .filter(|&(layer, row, col)| game.is_mine(layer, row, col))
+                .count();
+            assert_eq!(mines, 17);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! The bar above the board with the mine counter, the restart face and the timer, and the
+//! summary shown when a game ends.
+
+use nannou::prelude::*;
+
+use crate::{
+    constants::SCREEN_PADDING,
+    game::{GameStatus, Minesweeper},
+    skin::{rgb, Skin},
+};
+
+const FACE_SIZE: f32 = 60.;
+const COUNTER_WIDTH: f32 = 110.;
+const COUNTER_HEIGHT: f32 = 50.;
+
+/// Centre of the bar, in the padding at the top of a window `height` points high
+fn hud_y(height: f32) -> f32 {
+    height / 2. - SCREEN_PADDING / 2.
+}
+
+/// True when the point is on the bar, which covers the board when it is zoomed in
+pub fn is_on_hud(y: f32, height: f32) -> bool {
+    y >= height / 2. - SCREEN_PADDING
+}
+
+/// True when the point is on the restart face
+pub fn is_on_face(x: f32, y: f32, height: f32) -> bool {
+    x.hypot(y - hud_y(height)) <= FACE_SIZE / 2.
+}
+
+pub fn draw_hud(draw: &Draw, minesweeper: &Minesweeper, skin: &Skin, width: f32, height: f32) {
+    let y = hud_y(height);
+    let counter_x = width / 2. - SCREEN_PADDING - COUNTER_WIDTH / 2.;
+
+    draw.rect()
+        .w_h(width, SCREEN_PADDING)
+        .x_y(0., y)
+        .color(rgb(skin.theme.hud));
+
+    // Mines that are not flagged or already found by opening them, negative with too many flags
+    let mines_left =
+        minesweeper.mines() as i64 - minesweeper.flags() as i64 - minesweeper.mines_hit() as i64;
+    draw_counter(
+        draw,
+        skin,
+        -counter_x,
+        y,
+        &format!("{:03}", mines_left.clamp(-99, 999)),
+    );
+
+    let seconds = minesweeper.elapsed().as_secs().min(999);
+    draw_counter(draw, skin, counter_x, y, &format!("{seconds:03}"));
+
+    let face = match minesweeper.status() {
+        GameStatus::Playing => ":)",
+        GameStatus::Won => "B)",
+        GameStatus::Lost => "X(",
+    };
+    draw.ellipse()
+        .w_h(FACE_SIZE, FACE_SIZE)
+        .x_y(0., y)
+        .color(YELLOW)
+        .stroke(BLACK)
+        .stroke_weight(2.);
+    skin.text(draw, face)
+        .w_h(FACE_SIZE, FACE_SIZE)
+        .x_y(0., y)
+        .font_size(24)
+        .color(BLACK);
+}
+
+fn draw_counter(draw: &Draw, skin: &Skin, x: f32, y: f32, text: &str) {
+    draw.rect()
+        .w_h(COUNTER_WIDTH, COUNTER_HEIGHT)
+        .x_y(x, y)
+        .color(rgb(skin.theme.counter));
+    skin.text(draw, text)
+        .w_h(COUNTER_WIDTH, COUNTER_HEIGHT)
+        .x_y(x, y)
+        .font_size(36)
+        .color(rgb(skin.theme.counter_text));
+}
+
+/// How the game went, on a panel over the board
+pub fn draw_summary(draw: &Draw, minesweeper: &Minesweeper, skin: &Skin) {
+    let stats = minesweeper.stats();
+    let title = match minesweeper.status() {
+        GameStatus::Won => "You won!",
+        _ => "Game over",
+    };
+    let seconds = stats.time.as_secs_f32();
+    let mut lines = vec![
+        format!("Time: {seconds:.1}s"),
+        format!("Opened: {} of {} squares", stats.opened, stats.safe),
+        format!("Flags: {} of {} on mines", stats.correct_flags, stats.flags),
+        format!("3BV: {}", stats.three_bv),
+    ];
+    if stats.moves > 0 && seconds > 0. {
+        lines.push(format!(
+            "3BV/s: {:.2}   Efficiency: {:.0}%",
+            stats.three_bv as f32 / seconds,
+            stats.three_bv as f32 / stats.moves as f32 * 100.
+        ));
+    }
+
+    let (width, height) = (400., 120. + 30. * lines.len() as f32);
+    draw.rect()
+        .w_h(width, height)
+        .color(Rgba::new(0., 0., 0., 0.75));
+
+    let top = height / 2.;
+    skin.text(draw, title)
+        .w(width)
+        .x_y(0., top - 35.)
+        .font_size(32)
+        .color(WHITE);
+    for (i, line) in lines.iter().enumerate() {
+        skin.text(draw, line)
+            .w(width)
+            .x_y(0., top - 80. - 30. * i as f32)
+            .font_size(18)
+            .color(WHITE);
+    }
+    skin.text(
+        draw,
+        "N or the face for a new game, Esc to look at the board",
+    )
+    .w(width)
+    .x_y(0., -top + 20.)
+    .font_size(14)
+    .color(LIGHTGRAY);
+}
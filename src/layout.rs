@@ -0,0 +1,418 @@
+//! Strategies for where the mines go.
+//!
+//! A board lays out its mines with a [`LayoutStrategy`], given to
+//! [`Minesweeper::with_layout`](crate::game::Minesweeper::with_layout). The strategy gets a
+//! random number generator seeded from the board's seed, so the same seed, strategy and first
+//! click always give the same board.
+//!
+//! Strategies that can be rebuilt from their name alone are written to replays by name, see
+//! [`from_name`]. Replays of other strategies list every mine instead.
+
+use std::{fmt::Debug, path::Path, str::FromStr, sync::Arc};
+
+use rand::{rngs::SmallRng, Rng};
+
+use crate::{
+    game::MinesweeperError,
+    text::{split_rows, TextError},
+};
+
+pub trait LayoutStrategy: Debug + Send + Sync {
+    /// Picks `mines` different squares of a `rows` x `cols` board, none of them where
+    /// `excluded` is true. There are always enough squares left. All randomness has to come
+    /// from `rng`.
+    fn lay_out(
+        &self,
+        rows: usize,
+        cols: usize,
+        mines: usize,
+        excluded: &dyn Fn(usize, usize) -> bool,
+        rng: &mut SmallRng,
+    ) -> Vec<(usize, usize)>;
+
+    /// The name [`from_name`] rebuilds the strategy from, None if it can not
+    fn name(&self) -> Option<String> {
+        None
+    }
+}
+
+impl<L: LayoutStrategy + ?Sized> LayoutStrategy for Arc<L> {
+    fn lay_out(
+        &self,
+        rows: usize,
+        cols: usize,
+        mines: usize,
+        excluded: &dyn Fn(usize, usize) -> bool,
+        rng: &mut SmallRng,
+    ) -> Vec<(usize, usize)> {
+        (**self).lay_out(rows, cols, mines, excluded, rng)
+    }
+
+    fn name(&self) -> Option<String> {
+        (**self).name()
+    }
+}
+
+/// Rebuilds a strategy from its name: `uniform`, `clustered:CLUSTERS:SPREAD` or
+/// `relocating:NAME`
+pub fn from_name(name: &str) -> Result<Arc<dyn LayoutStrategy>, String> {
+    match name.split_once(':') {
+        None if name == "uniform" => Ok(Arc::new(Uniform)),
+        Some(("relocating", inner)) => Ok(Arc::new(Relocating(from_name(inner)?))),
+        Some(("clustered", args)) => {
+            let (clusters, spread) = args
+                .split_once(':')
+                .ok_or_else(|| format!("Expected clustered:CLUSTERS:SPREAD, got {name:?}"))?;
+            let clusters = clusters
+                .parse()
+                .map_err(|_| format!("Cluster count {clusters:?} is not a number"))?;
+            let spread = spread
+                .parse::<f32>()
+                .ok()
+                .filter(|spread| *spread > 0.)
+                .ok_or_else(|| format!("Spread {spread:?} is not a positive number"))?;
+            Ok(Arc::new(Clustered { clusters, spread }))
+        }
+        _ => Err(format!("Unknown layout {name:?}")),
+    }
+}
+
+/// Maps and patterns need at least one square, and every row as long as the first
+fn check_grid<T>(grid: &[Vec<T>]) -> Result<(), MinesweeperError> {
+    let cols = grid.first().map_or(0, Vec::len);
+    if cols == 0 {
+        return Err(MinesweeperError::EmptyBoard);
+    }
+    if let Some(row) = grid.iter().position(|grid_row| grid_row.len() != cols) {
+        return Err(MinesweeperError::UnevenLayout(row));
+    }
+    Ok(())
+}
+
+fn squares(rows: usize, cols: usize) -> impl Iterator<Item = (usize, usize)> {
+    (0..rows).flat_map(move |row| (0..cols).map(move |col| (row, col)))
+}
+
+/// Every free square is equally likely. Shuffles the free squares, so it is as fast on a full
+/// board as on an empty one.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Uniform;
+
+impl LayoutStrategy for Uniform {
+    fn lay_out(
+        &self,
+        rows: usize,
+        cols: usize,
+        mines: usize,
+        excluded: &dyn Fn(usize, usize) -> bool,
+        rng: &mut SmallRng,
+    ) -> Vec<(usize, usize)> {
+        let mut free = squares(rows, cols)
+            .filter(|&(row, col)| !excluded(row, col))
+            .collect::<Vec<_>>();
+
+        for i in 0..mines {
+            let j = rng.random_range(i..free.len());
+            free.swap(i, j);
+        }
+        free.truncate(mines);
+
+        free
+    }
+
+    fn name(&self) -> Option<String> {
+        Some("uniform".to_string())
+    }
+}
+
+/// Lays out the mines with another strategy without looking at which squares are excluded,
+/// then moves every mine on an excluded square to the top left most free square. This is how
+/// the classic Windows game keeps the first click safe, and what
+/// [`FirstClick::MoveToCorner`](crate::game::FirstClick::MoveToCorner) does.
+#[derive(Clone, Debug)]
+pub struct Relocating<L>(pub L);
+
+impl<L: LayoutStrategy> LayoutStrategy for Relocating<L> {
+    fn lay_out(
+        &self,
+        rows: usize,
+        cols: usize,
+        mines: usize,
+        excluded: &dyn Fn(usize, usize) -> bool,
+        rng: &mut SmallRng,
+    ) -> Vec<(usize, usize)> {
+        let mut placed = self.0.lay_out(rows, cols, mines, &|_, _| false, rng);
+
+        let mut taken = vec![false; rows * cols];
+        for &(row, col) in &placed {
+            taken[row * cols + col] = true;
+        }
+        let mut free = squares(rows, cols)
+            .filter(|&(row, col)| !excluded(row, col) && !taken[row * cols + col]);
+
+        for square in &mut placed {
+            if excluded(square.0, square.1) {
+                *square = free
+                    .next()
+                    .expect("There are always enough free squares for the mines");
+            }
+        }
+
+        placed
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(format!("relocating:{}", self.0.name()?))
+    }
+}
+
+/// Picks `mines` squares, where a square's chance to be picked grows with its weight. Squares
+/// with no weight are only used once every weighted square is taken.
+fn weighted(
+    rows: usize,
+    cols: usize,
+    mines: usize,
+    excluded: &dyn Fn(usize, usize) -> bool,
+    rng: &mut SmallRng,
+    weight: impl Fn(usize, usize) -> f32,
+) -> Vec<(usize, usize)> {
+    // Every square gets a random key and the highest keys are picked, which samples without
+    // replacement in proportion to the weights (Efraimidis and Spirakis)
+    let mut keyed = squares(rows, cols)
+        .filter(|&(row, col)| !excluded(row, col))
+        .map(|(row, col)| {
+            let weight = weight(row, col) as f64;
+            let u = 1. - rng.random::<f64>();
+            let key = if weight > 0. {
+                (true, u.ln() / weight)
+            } else {
+                (false, u)
+            };
+            (key, (row, col))
+        })
+        .collect::<Vec<_>>();
+
+    if mines > 0 && mines < keyed.len() {
+        keyed.select_nth_unstable_by(mines - 1, |(a, _), (b, _)| {
+            b.partial_cmp(a).expect("Keys are never NaN")
+        });
+    }
+    keyed.truncate(mines);
+
+    keyed.into_iter().map(|(_, square)| square).collect()
+}
+
+/// Mines follow a map of weights, the higher the weight the more likely a square is to get a
+/// mine. The map is stretched to fit the board, so one map works for every board size.
+#[derive(Clone, Debug)]
+pub struct DensityMap {
+    weights: Vec<Vec<f32>>,
+}
+
+impl DensityMap {
+    /// Fails if the map is empty, uneven, or has a negative or infinite weight
+    pub fn new(weights: Vec<Vec<f32>>) -> Result<Self, MinesweeperError> {
+        check_grid(&weights)?;
+        for (row, weights_row) in weights.iter().enumerate() {
+            if let Some(col) = weights_row.iter().position(|w| !w.is_finite() || *w < 0.) {
+                return Err(MinesweeperError::InvalidWeight { row, col });
+            }
+        }
+
+        Ok(DensityMap { weights })
+    }
+
+    /// The weight of a square on a `rows` x `cols` board
+    fn weight(&self, rows: usize, cols: usize, row: usize, col: usize) -> f32 {
+        let map_row = row * self.weights.len() / rows;
+        let map_col = col * self.weights[0].len() / cols;
+        self.weights[map_row][map_col]
+    }
+}
+
+impl LayoutStrategy for DensityMap {
+    fn lay_out(
+        &self,
+        rows: usize,
+        cols: usize,
+        mines: usize,
+        excluded: &dyn Fn(usize, usize) -> bool,
+        rng: &mut SmallRng,
+    ) -> Vec<(usize, usize)> {
+        weighted(rows, cols, mines, excluded, rng, |row, col| {
+            self.weight(rows, cols, row, col)
+        })
+    }
+}
+
+/// Mines gather around `clusters` random points. `spread` is how far in squares a cluster
+/// reaches, the lower it is the tighter the clusters.
+#[derive(Clone, Copy, Debug)]
+pub struct Clustered {
+    pub clusters: usize,
+    pub spread: f32,
+}
+
+/// Squares far away from every cluster still get a little weight, so the clusters never run
+/// out of room for the mines
+const BACKGROUND_WEIGHT: f32 = 0.01;
+
+impl LayoutStrategy for Clustered {
+    fn lay_out(
+        &self,
+        rows: usize,
+        cols: usize,
+        mines: usize,
+        excluded: &dyn Fn(usize, usize) -> bool,
+        rng: &mut SmallRng,
+    ) -> Vec<(usize, usize)> {
+        let centres = (0..self.clusters)
+            .map(|_| {
+                (
+                    rng.random_range(0. ..rows as f32),
+                    rng.random_range(0. ..cols as f32),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        weighted(rows, cols, mines, excluded, rng, |row, col| {
+            let cluster_weight = centres
+                .iter()
+                .map(|&(centre_row, centre_col)| {
+                    let distance_squared =
+                        (row as f32 - centre_row).powi(2) + (col as f32 - centre_col).powi(2);
+                    (-distance_squared / (2. * self.spread * self.spread)).exp()
+                })
+                .sum::<f32>();
+            BACKGROUND_WEIGHT + cluster_weight
+        })
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(format!("clustered:{}:{}", self.clusters, self.spread))
+    }
+}
+
+/// A fixed pattern of mines, tiled over the board from the top left corner. Mines go on the
+/// pattern's mines, picked at random when there are more of them than needed. If there are
+/// fewer, the rest are placed at random on the other squares.
+///
+/// Pattern files use the plain text mine layout of [`crate::text`], `*` for a mine and `.`
+/// for a safe square.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    mines: Vec<Vec<bool>>,
+}
+
+impl Pattern {
+    /// Fails if the pattern is empty or uneven
+    pub fn new(mines: Vec<Vec<bool>>) -> Result<Self, MinesweeperError> {
+        check_grid(&mines)?;
+
+        Ok(Pattern { mines })
+    }
+
+    /// A `rows` x `cols` pattern with a mine on each of `mines`
+    pub fn from_mines(
+        rows: usize,
+        cols: usize,
+        mines: &[(usize, usize)],
+    ) -> Result<Self, MinesweeperError> {
+        let mut pattern = vec![vec![false; cols]; rows];
+        for &(row, col) in mines {
+            *pattern
+                .get_mut(row)
+                .and_then(|pattern_row| pattern_row.get_mut(col))
+                .ok_or(MinesweeperError::OutOfBounds { row, col })? = true;
+        }
+        Pattern::new(pattern)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        std::fs::read_to_string(path)?
+            .parse()
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = TextError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = split_rows(s)?;
+
+        let mut mines = Vec::new();
+        for (row, symbols) in rows.iter().enumerate() {
+            let mut mines_row = Vec::new();
+            for (col, &symbol) in symbols.iter().enumerate() {
+                mines_row.push(match symbol {
+                    '*' => true,
+                    '.' => false,
+                    _ => return Err(TextError::UnknownSymbol { row, col, symbol }),
+                });
+            }
+            mines.push(mines_row);
+        }
+
+        // The rows are already checked to be even and not empty
+        Ok(Pattern { mines })
+    }
+}
+
+impl LayoutStrategy for Pattern {
+    fn lay_out(
+        &self,
+        rows: usize,
+        cols: usize,
+        mines: usize,
+        excluded: &dyn Fn(usize, usize) -> bool,
+        rng: &mut SmallRng,
+    ) -> Vec<(usize, usize)> {
+        let (pattern_rows, pattern_cols) = (self.mines.len(), self.mines[0].len());
+        weighted(rows, cols, mines, excluded, rng, |row, col| {
+            if self.mines[row % pattern_rows][col % pattern_cols] {
+                1.
+            } else {
+                0.
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn strategies_never_mine_an_excluded_square() {
+        let strategies: Vec<Arc<dyn LayoutStrategy>> = vec![
+            Arc::new(Uniform),
+            Arc::new(Relocating(Uniform)),
+            Arc::new(DensityMap::new(vec![vec![5., 0.], vec![0., 1.]]).unwrap()),
+            Arc::new(Clustered {
+                clusters: 2,
+                spread: 1.5,
+            }),
+            Arc::new("*.\n.*".parse::<Pattern>().unwrap()),
+        ];
+        let (rows, cols, mines) = (9, 12, 99);
+        // The 3 x 3 opening around the first click, with every other square mined
+        let excluded = |row: usize, col: usize| row.abs_diff(4) <= 1 && col.abs_diff(5) <= 1;
+
+        for strategy in strategies {
+            for seed in 0..20 {
+                let mut rng = SmallRng::seed_from_u64(seed);
+                let mut placed = strategy.lay_out(rows, cols, mines, &excluded, &mut rng);
+                assert!(
+                    placed.iter().all(|&(row, col)| !excluded(row, col)),
+                    "{strategy:?} mined the opening"
+                );
+                placed.sort_unstable();
+                placed.dedup();
+                assert_eq!(placed.len(), mines, "{strategy:?}");
+            }
+        }
+    }
+}
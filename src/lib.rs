@@ -1,4 +1,16 @@
 pub mod ai;
+mod bitset;
+pub mod camera;
+pub mod config;
 pub mod constants;
+pub mod endless;
 pub mod game;
-pub mod utils;
+pub mod game3d;
+pub mod layout;
+pub mod probability;
+pub mod replay;
+pub mod rules;
+pub mod solver;
+pub mod text;
+pub mod theme;
+pub mod topology;
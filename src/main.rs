@@ -1,49 +1,81 @@
-mod ai;
-mod constants;
-mod game;
+mod hud;
+mod settings;
+mod skin;
 mod utils;
 
-use std::collections::HashMap;
-
-use burn::{
-    data::dataloader::batcher::Batcher,
-    module::Module,
-    record::CompactRecorder,
-    tensor::{ElementConversion, Tensor},
+use std::{
+    collections::HashMap,
+    sync::mpsc::{self, Receiver},
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
+
+use burn::{data::dataloader::batcher::Batcher, module::Module, record::CompactRecorder};
 use burn_cuda::Cuda;
+use camera::Camera;
+use config::{Action, Args, Config, Preset, CONFIG_PATH};
 use constants::*;
-use game::{Minesweeper, Square};
+use game::{FirstClick, GameEvent, GameStatus, Minesweeper, MinesweeperError, MoveOutcome, Square};
+use minesweeper::{
+    ai, camera, config, constants, game, probability, replay, rules, solver, theme, topology,
+};
 use nannou::prelude::*;
+use replay::{Playback, Replay};
+use rules::Rule;
+use settings::{MenuAction, SettingsMenu};
+use skin::{rgb, Skin};
+use solver::Deduction;
+use theme::Theme;
+use topology::{Kernel, Topology};
 
 use crate::ai::{batcher::MinesweeperBatcher, model::ModelConfig};
 
-fn main() {
-    nannou::app(model).run();
-}
+/// How far the mouse has to move with a button held before it pans instead of clicking
+const DRAG_THRESHOLD: f32 = 4.;
 
-#[derive(Clone, Copy, Debug)]
-enum GameState {
-    Playing,
-    Lost,
-    Won,
+fn main() {
+    nannou::app(model).update(update).run();
 }
 
 type MyBackend = Cuda<f32, i32>;
+type NoGuessBoard = Result<(Minesweeper, usize), MinesweeperError>;
+
 struct Model {
-    game_state: GameState,
     minesweeper: Minesweeper,
+    events: Receiver<GameEvent>,
+    /// The flag and mine for themes without their own
     textures: HashMap<&'static str, wgpu::Texture>,
-    first_click: bool,
+    /// The built in theme first, then the ones in the assets
+    skins: Vec<Skin>,
+    skin: usize,
     ai_model: Option<ai::model::Model<MyBackend>>,
     ai_prediction: Option<Vec<f32>>,
+    /// Generate boards that can be solved without guessing
+    no_guess: bool,
+    /// A no guess board searched for on another thread, and the first click to make on it
+    rerolling: Option<(Receiver<NoGuessBoard>, (usize, usize))>,
+    hint: Option<Deduction>,
+    /// A replay given on the command line, and when it started playing
+    playback: Option<(Playback, Instant)>,
+    config: Config,
+    /// The settings overlay, when it is open
+    settings: Option<SettingsMenu>,
+    /// Show how the game went once it is over, until it is closed
+    summary: bool,
+    camera: Camera,
+    /// Where the mouse was pressed and the camera at that time, while a button is held
+    drag: Option<(Vec2, Camera)>,
+    dragged: bool,
+    /// A second button was pressed while the first was held
+    chording: bool,
+    /// The square keyboard moves are made on, shown once a key moves it
+    cursor: Option<(usize, usize)>,
 }
 
 fn model(app: &App) -> Model {
     let _window = app
         .new_window()
         .size(SCREEN_WIDTH, SCREEN_HEIGHT)
-        .resizable(false)
+        .min_size(500, 500)
         .title("Bombsearcher")
         .view(view)
         .event(event)
@@ -68,49 +100,313 @@ fn model(app: &App) -> Model {
         )
         .ok();
 
+    let args = Args::parse(std::env::args().skip(1)).unwrap_or_else(|err| {
+        println!("{err}");
+        Args::default()
+    });
+    let mut config = Config::load(CONFIG_PATH).unwrap_or_else(|err| {
+        println!("{err}");
+        Config::default()
+    });
+    if let Some(preset) = args.preset {
+        config.preset = preset;
+        save_config(&config);
+    }
+
+    let playback = args.replay.and_then(|path| match Replay::load(&path) {
+        Ok(replay) => match replay.playback() {
+            Ok(playback) => Some(playback),
+            Err(err) => {
+                println!("Could not play the replay: {err}");
+                None
+            }
+        },
+        Err(err) => {
+            println!("{err}");
+            None
+        }
+    });
+
+    let mut skins = vec![Skin::load(app, Theme::default())];
+    for dir in theme::find_themes(assets.join("themes")) {
+        match Theme::load(&dir) {
+            Ok(theme) => skins.push(Skin::load(app, theme)),
+            Err(err) => println!("Could not load the theme in {}: {err}", dir.display()),
+        }
+    }
+    let skin = skins
+        .iter()
+        .position(|skin| skin.theme.name.eq_ignore_ascii_case(&config.theme))
+        .unwrap_or_else(|| {
+            println!("No theme named {:?}, using the default", config.theme);
+            0
+        });
+
+    // A replay is played back on its own board, which is listened to like any other
+    let (playback, mut minesweeper) = match playback {
+        Some((playback, game)) => (Some((playback, Instant::now())), game),
+        // Player will always hit Empty on first click :D
+        None => (None, new_game(config.preset)),
+    };
+    let events = minesweeper.events();
+
     Model {
-        game_state: GameState::Playing,
-        minesweeper: Minesweeper::new_with_mines(MINES),
-        textures: HashMap::from([("bomb", bomb_texture), ("flag", flag_texture)]),
-        first_click: true,
+        minesweeper,
+        events,
+        textures: HashMap::from([("mine", bomb_texture), ("flag", flag_texture)]),
+        skins,
+        skin,
         ai_model,
         ai_prediction: None,
+        no_guess: false,
+        rerolling: None,
+        hint: None,
+        playback,
+        config,
+        settings: None,
+        summary: false,
+        camera: Camera::new(SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32),
+        drag: None,
+        dragged: false,
+        chording: false,
+        cursor: None,
+    }
+}
+
+fn update(app: &App, model: &mut Model, _update: Update) {
+    if let Some((pressed_at, camera)) = model.drag {
+        let moved = app.mouse.position() - pressed_at;
+        if moved.length() > DRAG_THRESHOLD {
+            model.dragged = true;
+        }
+        if model.dragged {
+            model.camera = camera;
+            model.camera.pan(moved.x, moved.y);
+        }
+    }
+
+    if let Some((receiver, (row, col))) = &model.rerolling {
+        if let Ok(found) = receiver.try_recv() {
+            let (row, col) = (*row, *col);
+            model.rerolling = None;
+            match found {
+                Ok((no_guess, attempts)) => {
+                    println!("Found a no guess board after {attempts} attempts");
+                    start_game(model, no_guess);
+                }
+                Err(err) => println!("{err}, you might have to guess"),
+            }
+            click(model, row, col);
+        }
+    }
+
+    for event in model.events.try_iter() {
+        announce(&event);
+        if matches!(event, GameEvent::Won | GameEvent::Lost) {
+            model.summary = true;
+        }
+    }
+
+    let Some((playback, started)) = &mut model.playback else {
+        return;
+    };
+
+    while playback
+        .next_at()
+        .is_some_and(|next_at| next_at <= started.elapsed())
+    {
+        if let Some(Err(err)) = playback.step(&mut model.minesweeper) {
+            println!("The replay does not match the board: {err}");
+            model.playback = None;
+            return;
+        }
+    }
+
+    if playback.next_at().is_none() {
+        println!("Replay finished");
+        model.playback = None;
     }
 }
 
 fn event(app: &App, model: &mut Model, event: WindowEvent) {
+    // Looked up before the settings borrow the model
+    let bound_action = match event {
+        WindowEvent::KeyPressed(key) => key_action(model, key),
+        _ => None,
+    };
+    if let Some(settings) = &mut model.settings {
+        let action = match event {
+            WindowEvent::KeyPressed(_) if bound_action == Some(Action::Settings) => {
+                MenuAction::Close
+            }
+            WindowEvent::KeyPressed(key) => settings.key(key, app.keys.mods.shift()),
+            WindowEvent::MousePressed(MouseButton::Left) => {
+                settings.click(app.mouse.x, app.mouse.y)
+            }
+            _ => MenuAction::None,
+        };
+        match action {
+            MenuAction::None => {}
+            MenuAction::Close => model.settings = None,
+            MenuAction::Pick(preset) => {
+                model.settings = None;
+                set_preset(model, preset);
+            }
+        }
+        return;
+    }
+
     match event {
-        WindowEvent::MousePressed(MouseButton::Left) => {
-            if let Some((row, col)) = utils::x_y_to_row_col(app.mouse.x, app.mouse.y) {
-                // Player will always hit Empty on first click :D
-                while model.first_click
-                    && !matches!(model.minesweeper.square_state(row, col), Square::Empty)
-                {
-                    model.minesweeper = Minesweeper::new_with_mines(MINES);
-                }
-                model.first_click = false;
+        WindowEvent::MousePressed(MouseButton::Left)
+            if hud::is_on_face(app.mouse.x, app.mouse.y, model.camera.height()) =>
+        {
+            new_game_like_this(model);
+        }
+        WindowEvent::MousePressed(_) => {
+            if model.drag.is_some() {
+                model.chording = true;
+            } else {
+                model.drag = Some((app.mouse.position(), model.camera));
+                model.dragged = false;
+            }
+        }
+        // A press and release without dragging in between is a click. Releasing either button
+        // after pressing both chords, and the other release is then ignored.
+        WindowEvent::MouseReleased(button) => {
+            let chording = std::mem::take(&mut model.chording);
+            if model.drag.take().is_none() || model.dragged || model.playback.is_some() {
+                return;
+            }
+            let Some((row, col)) = square_at(model, app.mouse.x, app.mouse.y) else {
+                return;
+            };
 
-                if model.minesweeper.is_square_marked(row, col) {
-                    return;
-                }
-                model.minesweeper.click(row, col);
+            match button {
+                _ if chording => chord(model, row, col),
+                MouseButton::Left => open(model, row, col),
+                MouseButton::Right => report(&model.minesweeper.mark(row, col)),
+                MouseButton::Middle => chord(model, row, col),
+                _ => {}
+            }
+        }
+        WindowEvent::MouseWheel(delta, _) => {
+            let lines = match delta {
+                MouseScrollDelta::LineDelta(_, y) => y,
+                MouseScrollDelta::PixelDelta(position) => position.y as f32 / 50.,
+            };
+            model
+                .camera
+                .zoom_at(1.1f32.powf(lines), app.mouse.x, app.mouse.y);
+        }
+        WindowEvent::Resized(size) => model.camera.resize(size.x, size.y),
+        WindowEvent::KeyPressed(Key::Escape) if model.summary => model.summary = false,
+        WindowEvent::KeyPressed(Key::Z) if app.keys.mods.ctrl() => {
+            model.minesweeper.undo();
+            model.hint = None;
+        }
+        WindowEvent::KeyPressed(Key::Y) if app.keys.mods.ctrl() => {
+            model.minesweeper.redo();
+            model.hint = None;
+        }
+        WindowEvent::KeyPressed(Key::S) if app.keys.mods.ctrl() => {
+            let secs = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs());
+            let path = format!("replays/replay-{secs}.replay");
 
-                if model.minesweeper.is_board_completed() {
-                    println!("Yey");
-                }
-                // dbg!(model.minesweeper.get_category_vec());
+            match std::fs::create_dir_all("replays")
+                .and_then(|_| Replay::from_game(&model.minesweeper).save(&path))
+            {
+                Ok(()) => println!("Saved replay to {path}"),
+                Err(err) => println!("Could not save replay: {err}"),
             }
         }
-        WindowEvent::MousePressed(MouseButton::Right) => {
-            if let Some((row, col)) = utils::x_y_to_row_col(app.mouse.x, app.mouse.y) {
-                if model.minesweeper.is_square_open(row, col) {
-                    return;
-                }
+        // Puzzle rules, also only before the first move
+        WindowEvent::KeyPressed(key @ (Key::F1 | Key::F2 | Key::F3 | Key::F4 | Key::F5))
+            if model.minesweeper.history().is_empty() =>
+        {
+            let mines = model.minesweeper.mines();
+            let rule = match key {
+                Key::F1 => Rule::Liar,
+                Key::F2 => Rule::DoubleMines(mines / 5),
+                Key::F3 => Rule::Orthogonal,
+                Key::F4 => Rule::FlagCap(mines),
+                _ => Rule::Lives(3),
+            };
+            toggle_rule(model, rule);
+        }
+        WindowEvent::KeyPressed(_) if !app.keys.mods.ctrl() => {
+            if let Some(action) = bound_action {
+                act(model, action);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The action bound to the key in the config
+fn key_action(model: &Model, key: Key) -> Option<Action> {
+    model.config.keys.action(&format!("{key:?}"))
+}
 
-                model.minesweeper.mark(row, col);
+/// Does what a bound key asks for
+fn act(model: &mut Model, action: Action) {
+    match action {
+        Action::CursorLeft => move_cursor(model, 0, -1),
+        Action::CursorRight => move_cursor(model, 0, 1),
+        // Row 0 is at the bottom of the window
+        Action::CursorUp => move_cursor(model, 1, 0),
+        Action::CursorDown => move_cursor(model, -1, 0),
+        Action::Open | Action::Flag | Action::Chord if model.playback.is_none() => {
+            // The first key press only shows where the cursor is
+            let Some((row, col)) = model.cursor else {
+                move_cursor(model, 0, 0);
+                return;
+            };
+            match action {
+                Action::Open => open(model, row, col),
+                Action::Flag => report(&model.minesweeper.mark(row, col)),
+                _ => chord(model, row, col),
             }
         }
-        WindowEvent::KeyPressed(Key::M) => {
+        Action::Open | Action::Flag | Action::Chord => {}
+        Action::NewGame => new_game_like_this(model),
+        // The topology can only be changed before the first move
+        Action::Topology if model.minesweeper.history().is_empty() => {
+            let mut topology = model.minesweeper.topology().clone();
+            topology.kernel = match topology.kernel {
+                Kernel::Square => Kernel::Cross,
+                Kernel::Cross => Kernel::Knight,
+                Kernel::Knight => Kernel::Hex,
+                Kernel::Hex | Kernel::Custom(_) => Kernel::Square,
+            };
+            set_topology(model, topology);
+        }
+        Action::Wrap if model.minesweeper.history().is_empty() => {
+            let mut topology = model.minesweeper.topology().clone();
+            topology.wrap = !topology.wrap;
+            set_topology(model, topology);
+        }
+        Action::Topology | Action::Wrap => {}
+        Action::Settings => model.settings = Some(SettingsMenu::new(model.config.preset)),
+        Action::NoGuess => {
+            model.no_guess = !model.no_guess;
+            println!("No guess boards: {}", model.no_guess);
+        }
+        Action::Hint => {
+            model.hint = solver::hint(&model.minesweeper);
+            if model.hint.is_none() {
+                println!("No square is certainly safe, you have to guess");
+            }
+        }
+        Action::Probabilities => {
+            // Exact probabilities in the same overlay as the AI, to compare the two
+            model.ai_prediction = probability::mine_probabilities(&model.minesweeper);
+            if model.ai_prediction.is_none() {
+                println!("The board does not add up or is too big to work out, no probabilities.");
+            }
+        }
+        Action::Ai => {
             if model.ai_model.is_none() {
                 println!("AI model is not loaded.");
                 return;
@@ -121,77 +417,425 @@ fn event(app: &App, model: &mut Model, event: WindowEvent) {
                 .boards;
 
             let forward = ai_model.forward(board_state);
-            let prediction = forward.reshape([constants::ROWS, constants::COLS]);
+            let prediction = forward.reshape([model.minesweeper.rows(), model.minesweeper.cols()]);
             model.ai_prediction = Some(prediction.to_data().iter().collect());
         }
+        Action::ZoomIn => {
+            model.camera.zoom_at(1.5, 0., 0.);
+            follow_cursor(model);
+        }
+        Action::ZoomOut => {
+            model.camera.zoom_at(1. / 1.5, 0., 0.);
+            follow_cursor(model);
+        }
+        Action::ResetView => model.camera.reset(),
+        Action::Theme => {
+            model.skin = (model.skin + 1) % model.skins.len();
+            let name = &model.skins[model.skin].theme.name;
+            println!("Theme: {name}");
+            model.config.theme = name.clone();
+            save_config(&model.config);
+        }
+    }
+}
+
+/// Moves the keyboard cursor, which starts in the middle of the board, and keeps it in view
+fn move_cursor(model: &mut Model, rows_up: isize, cols_right: isize) {
+    let (rows, cols) = (model.minesweeper.rows(), model.minesweeper.cols());
+    model.cursor = Some(match model.cursor {
+        Some((row, col)) => (
+            row.saturating_add_signed(rows_up).min(rows - 1),
+            col.saturating_add_signed(cols_right).min(cols - 1),
+        ),
+        None => (rows / 2, cols / 2),
+    });
+    follow_cursor(model);
+}
+
+fn follow_cursor(model: &mut Model) {
+    if let Some((row, col)) = model.cursor {
+        let (x, y) = square_x_y(model, row, col);
+        model.camera.follow(x, y);
+    }
+}
+
+fn is_hex(minesweeper: &Minesweeper) -> bool {
+    minesweeper.topology().kernel == Kernel::Hex
+}
+
+/// The centre of a square on the board, before the camera moves it
+fn square_x_y(model: &Model, row: usize, col: usize) -> (f32, f32) {
+    let (rows, cols) = (model.minesweeper.rows(), model.minesweeper.cols());
+    let area = model.camera.board_area();
+    if is_hex(&model.minesweeper) {
+        utils::hex_row_col_to_x_y(row, col, rows, cols, area)
+    } else {
+        utils::row_col_to_x_y(row, col, rows, cols, area)
+    }
+}
+
+/// The square under the screen point, hex boards have their own layout
+fn square_at(model: &Model, x: f32, y: f32) -> Option<(usize, usize)> {
+    if hud::is_on_hud(y, model.camera.height()) {
+        return None;
+    }
+
+    let (rows, cols) = (model.minesweeper.rows(), model.minesweeper.cols());
+    let (x, y) = model.camera.to_board(x, y);
+    let area = model.camera.board_area();
+    if is_hex(&model.minesweeper) {
+        utils::x_y_to_hex_row_col(x, y, rows, cols, area)
+    } else {
+        utils::x_y_to_row_col(x, y, rows, cols, area)
+    }
+}
+
+fn open(model: &mut Model, row: usize, col: usize) {
+    // The first click is made once the no guess board is found
+    if model.minesweeper.is_square_marked(row, col) || model.rerolling.is_some() {
+        return;
+    }
+
+    if model.no_guess && !model.minesweeper.mines_placed() {
+        // Looking for a board can take seconds on big boards, so the window keeps drawing
+        let restarted = model.minesweeper.restart(rand::random());
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            // Nobody is waiting for the board any more when a new game was started
+            let _ = sender.send(restarted.reroll_until_no_guess(row, col, NO_GUESS_MAX_ATTEMPTS));
+        });
+        model.rerolling = Some((receiver, (row, col)));
+        println!("Looking for a no guess board");
+        return;
+    }
+    click(model, row, col);
+}
+
+fn click(model: &mut Model, row: usize, col: usize) {
+    let outcome = model.minesweeper.click(row, col);
+    model.hint = None;
+    report(&outcome);
+}
+
+fn set_topology(model: &mut Model, topology: Topology) {
+    let restarted = model.minesweeper.restart(rand::random());
+    match restarted.with_topology(topology.clone()) {
+        Ok(minesweeper) => start_game(model, minesweeper),
+        Err(_) => {
+            println!("The board is too small for {topology}");
+            return;
+        }
+    }
+
+    println!("Topology: {topology}");
+}
+
+fn toggle_rule(model: &mut Model, rule: Rule) {
+    let mut rules = model.minesweeper.rules().to_vec();
+    match rules.iter().position(|active| *active == rule) {
+        Some(i) => {
+            rules.remove(i);
+        }
+        None => rules.push(rule),
+    }
+
+    let rule_names = rules.iter().map(Rule::to_string).collect::<Vec<_>>();
+    println!("Rules: {}", rule_names.join(", "));
+    let minesweeper = model.minesweeper.restart(rand::random()).with_rules(rules);
+    start_game(model, minesweeper);
+}
+
+/// A classic board of the preset's size, the first click always opens up
+fn new_game(preset: Preset) -> Minesweeper {
+    let (rows, cols, mines) = preset.dimensions();
+    Minesweeper::new_deferred(rows, cols, mines, FirstClick::Opening)
+        .expect("Presets fit their mines")
+}
+
+/// Starts a new game with `preset` and remembers it for the next launch
+fn set_preset(model: &mut Model, preset: Preset) {
+    model.playback = None;
+    model.config.preset = preset;
+    save_config(&model.config);
+    start_game(model, new_game(preset));
+
+    println!("Preset: {preset}");
+}
+
+/// Starts over on a new board with the same size, topology and rules
+fn new_game_like_this(model: &mut Model) {
+    model.playback = None;
+    let minesweeper = model.minesweeper.restart(rand::random());
+    start_game(model, minesweeper);
+}
+
+fn save_config(config: &Config) {
+    if let Err(err) = config.save(CONFIG_PATH) {
+        println!("Could not save settings: {err}");
+    }
+}
+
+/// Plays on `minesweeper` from now on, listening to its events
+fn start_game(model: &mut Model, mut minesweeper: Minesweeper) {
+    model.events = minesweeper.events();
+    model.rerolling = None;
+    model.minesweeper = minesweeper;
+    model.hint = None;
+    model.ai_prediction = None;
+    model.summary = false;
+    model.camera.reset();
+    let (rows, cols) = (model.minesweeper.rows(), model.minesweeper.cols());
+    model.cursor = model
+        .cursor
+        .map(|(row, col)| (row.min(rows - 1), col.min(cols - 1)));
+}
+
+fn chord(model: &mut Model, row: usize, col: usize) {
+    let outcome = model.minesweeper.chord(row, col);
+    model.hint = None;
+    report(&outcome);
+}
+
+/// Moves that could not be made are only worth telling about when it is not obvious why
+fn report(outcome: &Result<MoveOutcome, MinesweeperError>) {
+    if let Err(err @ MinesweeperError::NoFlagsLeft) = outcome {
+        println!("{err}");
+    }
+}
+
+fn announce(event: &GameEvent) {
+    match event {
+        GameEvent::MineHit { row, col } => println!("Hit the mine at ({row}, {col})"),
+        GameEvent::Won => println!("Yey"),
+        GameEvent::Lost => println!("Game over"),
         _ => {}
     }
 }
 
 fn view(app: &App, model: &Model, frame: Frame) {
     let draw = app.draw();
-    draw.background().color(GRAY);
+    let skin = &model.skins[model.skin];
+    let theme = &skin.theme;
+    draw.background().color(rgb(theme.background));
 
-    for row in 0..ROWS {
-        for col in 0..COLS {
-            let (x, y) = utils::row_col_to_x_y(row, col);
+    let rows = model.minesweeper.rows();
+    let cols = model.minesweeper.cols();
+    let hex = is_hex(&model.minesweeper);
+    let status = model.minesweeper.status();
+    let game_over = status != GameStatus::Playing;
+    let camera = &model.camera;
+    let area = camera.board_area();
+    // Sizes on screen, the layout is scaled by the camera's zoom
+    let hex_size = utils::hex_size(rows, cols, area) * camera.zoom();
+    // Numbers and textures are drawn in a square that fits inside each hexagon
+    let square_size = if hex {
+        hex_size * 1.2
+    } else {
+        utils::square_size(rows, cols, area) * camera.zoom()
+    };
+    let (square_width, square_height) = (square_size, square_size);
+    let number_size = (square_size * theme.number_scale).max(1.) as u32;
+    // The theme's textures, and the flag and mine from the assets when it has none
+    let texture = |name: &str| skin.texture(name).or_else(|| model.textures.get(name));
+    let draw_texture = |texture: &wgpu::Texture, x: f32, y: f32| {
+        draw.texture(texture)
+            .w_h(square_width, square_height)
+            .x_y(x, y);
+    };
+    // Hexagons are always drawn in colour, textures are square
+    let draw_square = |x: f32, y: f32, color: Rgb, texture: Option<&wgpu::Texture>| {
+        if hex {
+            let corners = utils::hexagon(x, y, hex_size - SQUARE_MARGIN * camera.zoom() / 2.);
+            draw.polygon().points(corners).color(color);
+        } else if let Some(texture) = texture {
+            draw_texture(texture, x, y);
+        } else {
+            draw.rect()
+                .w_h(square_width, square_height)
+                .x_y(x, y)
+                .color(color);
+        }
+    };
+
+    for row in 0..rows {
+        for col in 0..cols {
+            let (x, y) = square_x_y(model, row, col);
+            let (x, y) = camera.to_screen(x, y);
+            if !camera.is_visible(x, y, if hex { hex_size * 2. } else { square_size }) {
+                continue;
+            }
 
             if !model.minesweeper.is_square_open(row, col) {
-                let color = if let Some(prediction) = &model.ai_prediction {
-                    let v = prediction[row * constants::COLS + col].clamp(0.0, 1.0);
-                    Rgb::new(1., 1. - v, 1. - v)
+                if let Some(prediction) = &model.ai_prediction {
+                    let v = prediction[row * cols + col];
+                    draw_square(x, y, rgb(theme.closed.mix(theme.heat, v)), None);
+                } else if model
+                    .hint
+                    .as_ref()
+                    .is_some_and(|hint| hint.square == (row, col))
+                {
+                    draw_square(x, y, rgb(theme.hint), None);
                 } else {
-                    Rgb::new(1., 1., 1.)
-                };
-                draw.rect()
-                    .w_h(SQUARE_WIDTH, SQUARE_HEIGHT)
-                    .x_y(x, y)
-                    .color(color);
+                    draw_square(x, y, rgb(theme.closed), skin.texture("closed"));
+                    if theme.bevel && !hex && skin.texture("closed").is_none() {
+                        draw_bevel(&draw, x, y, square_size);
+                    }
+                }
 
                 if let Some(prediction) = &model.ai_prediction {
-                    let v = prediction[row * constants::COLS + col];
-                    draw.text(&format!("{:.2}", v))
-                        .w_h(SQUARE_WIDTH, SQUARE_HEIGHT)
-                        .w_h(SQUARE_WIDTH, SQUARE_HEIGHT)
+                    let v = prediction[row * cols + col];
+                    skin.text(&draw, &format!("{:.2}", v))
+                        .w_h(square_width, square_height)
                         .x_y(x, y)
-                        .font_size(12)
-                        .color(GREEN);
+                        .font_size(number_size / 2)
+                        .color(rgb(theme.probability));
                 }
 
-                if model.minesweeper.is_square_marked(row, col) {
-                    draw.texture(model.textures.get("flag").unwrap())
-                        .w_h(SQUARE_WIDTH, SQUARE_HEIGHT)
-                        .x_y(x, y);
+                let marked = model.minesweeper.is_square_marked(row, col);
+                let mine = model.minesweeper.is_mine(row, col);
+                // Once the game is over every mine is shown, flagged after a win
+                let revealed = game_over && mine && !marked;
+                if marked || (revealed && status == GameStatus::Won) {
+                    draw_texture(texture("flag").unwrap(), x, y);
+                } else if revealed {
+                    draw_texture(texture("mine").unwrap(), x, y);
+                }
+
+                // Flags on squares without a mine are crossed out
+                if game_over && marked && !mine {
+                    if let Some(wrong_flag) = skin.texture("wrong_flag") {
+                        draw_texture(wrong_flag, x, y);
+                    } else {
+                        let (half_width, half_height) = (square_width / 2., square_height / 2.);
+                        for (from, to) in [
+                            (pt2(-half_width, -half_height), pt2(half_width, half_height)),
+                            (pt2(-half_width, half_height), pt2(half_width, -half_height)),
+                        ] {
+                            draw.line()
+                                .start(pt2(x, y) + from)
+                                .end(pt2(x, y) + to)
+                                .weight(3.)
+                                .color(rgb(theme.mine));
+                        }
+                    }
                 }
             } else {
-                let square_state = model.minesweeper.square_state(row, col);
-                let background_color = match square_state {
-                    Square::Empty | Square::Nearby(_) => Rgb::new(0.3, 0.3, 0.3),
-                    Square::Mine => Rgb::new(0.8, 0.3, 0.3),
+                let Some(square_state) = model.minesweeper.square_state(row, col) else {
+                    continue;
                 };
-
-                draw.rect()
-                    .w_h(SQUARE_WIDTH, SQUARE_HEIGHT)
-                    .x_y(x, y)
-                    .color(background_color);
+                match square_state {
+                    Square::Empty | Square::Nearby(_) => {
+                        draw_square(x, y, rgb(theme.open), skin.texture("open"));
+                    }
+                    Square::Mine => draw_square(x, y, rgb(theme.mine), skin.texture("mine_hit")),
+                }
 
                 match square_state {
                     Square::Empty => {}
-                    Square::Nearby(v) => {
-                        draw.text(&v.to_string())
-                            .w_h(SQUARE_WIDTH, SQUARE_HEIGHT)
-                            .x_y(x, y)
-                            .font_size(24)
-                            .color(WHITE);
-                    }
-                    Square::Mine => {
-                        draw.texture(model.textures.get("bomb").unwrap())
-                            .w_h(SQUARE_WIDTH, SQUARE_HEIGHT)
-                            .x_y(x, y);
-                    }
+                    Square::Nearby(v) => match skin.texture(&v.to_string()) {
+                        Some(number) => draw_texture(number, x, y),
+                        None => {
+                            skin.text(&draw, &v.to_string())
+                                .w_h(square_width, square_height)
+                                .x_y(x, y)
+                                .font_size(number_size)
+                                .color(rgb(theme.number(v)));
+                        }
+                    },
+                    // A mine_hit texture already shows the mine
+                    Square::Mine if skin.texture("mine_hit").is_some() && !hex => {}
+                    Square::Mine => draw_texture(texture("mine").unwrap(), x, y),
                 }
             }
         }
     }
+
+    if let Some((row, col)) = model.cursor {
+        let (x, y) = square_x_y(model, row, col);
+        let (x, y) = camera.to_screen(x, y);
+        if hex {
+            draw.polyline()
+                .weight(3.)
+                .points_closed(utils::hexagon(x, y, hex_size))
+                .color(rgb(theme.cursor));
+        } else {
+            draw.rect()
+                .w_h(square_width, square_height)
+                .x_y(x, y)
+                .no_fill()
+                .stroke(rgb(theme.cursor))
+                .stroke_weight(3.);
+        }
+    }
+
+    hud::draw_hud(
+        &draw,
+        &model.minesweeper,
+        skin,
+        camera.width(),
+        camera.height(),
+    );
+    if model.summary && game_over {
+        hud::draw_summary(&draw, &model.minesweeper, skin);
+    }
+    if let Some(settings) = &model.settings {
+        settings.draw(&draw, skin, camera.width(), camera.height());
+    }
     draw.to_frame(app, &frame).unwrap();
 }
+
+/// Light top and left edges and dark bottom and right edges, so the square looks raised
+fn draw_bevel(draw: &Draw, x: f32, y: f32, size: f32) {
+    let half = size / 2.;
+    let edge = (size * 0.12).max(1.);
+    let (outer, inner) = (half, half - edge);
+    let light = Rgb::new(1., 1., 1.);
+    let dark = Rgb::new(0.5, 0.5, 0.5);
+
+    for (corners, color) in [
+        // Top
+        (
+            [
+                (-outer, outer),
+                (outer, outer),
+                (inner, inner),
+                (-inner, inner),
+            ],
+            light,
+        ),
+        // Left
+        (
+            [
+                (-outer, outer),
+                (-inner, inner),
+                (-inner, -inner),
+                (-outer, -outer),
+            ],
+            light,
+        ),
+        // Bottom
+        (
+            [
+                (-outer, -outer),
+                (-inner, -inner),
+                (inner, -inner),
+                (outer, -outer),
+            ],
+            dark,
+        ),
+        // Right
+        (
+            [
+                (outer, -outer),
+                (inner, -inner),
+                (inner, inner),
+                (outer, outer),
+            ],
+            dark,
+        ),
+    ] {
+        draw.polygon()
+            .points(corners.map(|(dx, dy)| (x + dx, y + dy)))
+            .color(color);
+    }
+}
@@ -0,0 +1,405 @@
+//! Exact mine probabilities from the visible state of a board.
+//!
+//! The closed squares next to opened numbers (the frontier) are split into groups that share no
+//! numbers. Every mine layout of a group that agrees with its numbers is counted, and the
+//! groups are combined with the squares away from the frontier, which can hold the rest of
+//! the mines in any way. Flags are ignored, as they might be wrong.
+//!
+//! Counting layouts takes time exponential in the size of a group, so the search gives up
+//! after [`PROBABILITY_MAX_NODES`] steps over all groups. This keeps a big frontier in the
+//! middle of an expert game from hanging the caller.
+
+use crate::{
+    constants::PROBABILITY_MAX_NODES,
+    game::{Minesweeper, Square},
+};
+
+/// A group of frontier squares, and how many mine layouts of them there are
+struct Component {
+    squares: Vec<(usize, usize)>,
+    /// `layouts[k]` is the amount of layouts with `k` mines
+    layouts: Vec<f64>,
+    /// `mine_layouts[k][i]` is the amount of layouts with `k` mines where square `i` is a mine
+    mine_layouts: Vec<Vec<f64>>,
+}
+
+/// The chance of a mine on every square, in the same row major order as the GUI's
+/// `ai_prediction` overlay. Opened squares are 0.
+///
+/// Returns None if no layout of the remaining mines agrees with the visible numbers, if the
+/// board's rules make numbers inexact, like [`Rule::Liar`](crate::rules::Rule::Liar), or if
+/// the frontier is too big to search within [`PROBABILITY_MAX_NODES`] steps.
+pub fn mine_probabilities(game: &Minesweeper) -> Option<Vec<f32>> {
+    if !game.has_exact_numbers() {
+        return None;
+    }
+
+    let cols = game.cols();
+    let constraints = constraints(game);
+
+    let mut in_frontier = vec![vec![false; cols]; game.rows()];
+    for (squares, _) in &constraints {
+        for &(row, col) in squares {
+            in_frontier[row][col] = true;
+        }
+    }
+
+    let interior = (0..game.rows())
+        .flat_map(|row| (0..cols).map(move |col| (row, col)))
+        .filter(|&(row, col)| !game.is_square_open(row, col) && !in_frontier[row][col])
+        .collect::<Vec<_>>();
+    let opened_mines = (0..game.rows())
+        .flat_map(|row| (0..cols).map(move |col| (row, col)))
+        .filter(|&(row, col)| {
+            game.is_square_open(row, col) && matches!(game.shown(row, col), Square::Mine)
+        })
+        .count();
+    // A board can claim fewer mines than it shows, then no layout agrees with it
+    let mines = game.mines().checked_sub(opened_mines)?;
+
+    let mut nodes_left = PROBABILITY_MAX_NODES;
+    let components = components(&constraints)
+        .into_iter()
+        .map(|component| count_layouts(component, &constraints, &mut nodes_left))
+        .collect::<Option<Vec<_>>>()?;
+
+    // Weight of putting `m` mines on the interior, relative to the most likely amount
+    let ln_choose_interior = (0..=mines)
+        .map(|m| ln_choose(interior.len(), m))
+        .collect::<Vec<_>>();
+    let max_ln = ln_choose_interior
+        .iter()
+        .copied()
+        .fold(f64::NEG_INFINITY, f64::max);
+    let interior_weight = |frontier_mines: usize| {
+        mines
+            .checked_sub(frontier_mines)
+            .map_or(0., |m| (ln_choose_interior[m] - max_ln).exp())
+    };
+
+    let all_layouts = components.iter().fold(vec![1.], |acc, component| {
+        convolve(&acc, &component.layouts)
+    });
+    let total = all_layouts
+        .iter()
+        .enumerate()
+        .map(|(k, layouts)| layouts * interior_weight(k))
+        .sum::<f64>();
+
+    if total == 0. || !total.is_finite() {
+        return None;
+    }
+
+    let mut probabilities = vec![0.; game.rows() * cols];
+
+    if !interior.is_empty() {
+        let interior_mines = all_layouts
+            .iter()
+            .enumerate()
+            .filter(|&(k, _)| k <= mines)
+            .map(|(k, layouts)| layouts * interior_weight(k) * (mines - k) as f64)
+            .sum::<f64>();
+        let probability = interior_mines / total / interior.len() as f64;
+
+        for (row, col) in interior {
+            probabilities[row * cols + col] = probability as f32;
+        }
+    }
+
+    for (i, component) in components.iter().enumerate() {
+        let other_layouts = components
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| j != i)
+            .fold(vec![1.], |acc, (_, other)| convolve(&acc, &other.layouts));
+
+        for (square_i, &(row, col)) in component.squares.iter().enumerate() {
+            let mut weight = 0.;
+            for (k, mine_layouts) in component.mine_layouts.iter().enumerate() {
+                let layouts_with_mine = mine_layouts[square_i];
+                if layouts_with_mine == 0. {
+                    continue;
+                }
+
+                weight += layouts_with_mine
+                    * other_layouts
+                        .iter()
+                        .enumerate()
+                        .map(|(other_k, layouts)| layouts * interior_weight(k + other_k))
+                        .sum::<f64>();
+            }
+
+            probabilities[row * cols + col] = (weight / total) as f32;
+        }
+    }
+
+    Some(probabilities)
+}
+
+/// The closed squares around every opened number, and how many of them are mines
+fn constraints(game: &Minesweeper) -> Vec<(Vec<(usize, usize)>, usize)> {
+    let mut constraints = Vec::new();
+
+    for row in 0..game.rows() {
+        for col in 0..game.cols() {
+            if !game.is_square_open(row, col) {
+                continue;
+            }
+
+            let mines = match game.shown(row, col) {
+                Square::Empty => 0,
+                Square::Nearby(n) => n as usize,
+                Square::Mine => continue,
+            };
+
+            let mut squares = Vec::new();
+            let mut mines_left = mines;
+            for (n_row, n_col) in game.counted_neighbours(row, col) {
+                if !game.is_square_open(n_row, n_col) {
+                    squares.push((n_row, n_col));
+                } else if matches!(game.shown(n_row, n_col), Square::Mine) {
+                    mines_left = mines_left.saturating_sub(1);
+                }
+            }
+
+            if !squares.is_empty() {
+                constraints.push((squares, mines_left));
+            }
+        }
+    }
+
+    constraints
+}
+
+/// Splits the constraints into groups that share no squares. Returns the constraint indices
+/// of every group.
+fn components(constraints: &[(Vec<(usize, usize)>, usize)]) -> Vec<Vec<usize>> {
+    let mut parent = (0..constraints.len()).collect::<Vec<_>>();
+
+    fn find(parent: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while parent[root] != root {
+            root = parent[root];
+        }
+        parent[i] = root;
+        root
+    }
+
+    for a in 0..constraints.len() {
+        for b in a + 1..constraints.len() {
+            if constraints[a]
+                .0
+                .iter()
+                .any(|square| constraints[b].0.contains(square))
+            {
+                let root_a = find(&mut parent, a);
+                let root_b = find(&mut parent, b);
+                parent[root_a] = root_b;
+            }
+        }
+    }
+
+    let mut components: Vec<(usize, Vec<usize>)> = Vec::new();
+    for i in 0..constraints.len() {
+        let root = find(&mut parent, i);
+        match components.iter_mut().find(|(r, _)| *r == root) {
+            Some((_, members)) => members.push(i),
+            None => components.push((root, vec![i])),
+        }
+    }
+
+    components.into_iter().map(|(_, members)| members).collect()
+}
+
+/// Counts every mine layout of a group that agrees with all of its numbers, None if that takes
+/// more than `nodes_left` steps
+fn count_layouts(
+    component: Vec<usize>,
+    constraints: &[(Vec<(usize, usize)>, usize)],
+    nodes_left: &mut usize,
+) -> Option<Component> {
+    // Squares are added in constraint order, so constraints are completed early in the search
+    let mut squares: Vec<(usize, usize)> = Vec::new();
+    for &c in &component {
+        for square in &constraints[c].0 {
+            if !squares.contains(square) {
+                squares.push(*square);
+            }
+        }
+    }
+
+    let square_constraints = squares
+        .iter()
+        .map(|square| {
+            component
+                .iter()
+                .enumerate()
+                .filter(|(_, &c)| constraints[c].0.contains(square))
+                .map(|(local, _)| local)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut search = Search {
+        square_constraints,
+        mines_needed: component.iter().map(|&c| constraints[c].1).collect(),
+        unassigned: component.iter().map(|&c| constraints[c].0.len()).collect(),
+        is_mine: vec![false; squares.len()],
+        layouts: vec![0.; squares.len() + 1],
+        mine_layouts: vec![vec![0.; squares.len()]; squares.len() + 1],
+        nodes_left: *nodes_left,
+    };
+    let finished = search.run(0, 0);
+    *nodes_left = search.nodes_left;
+    if !finished {
+        return None;
+    }
+
+    Some(Component {
+        squares,
+        layouts: search.layouts,
+        mine_layouts: search.mine_layouts,
+    })
+}
+
+struct Search {
+    square_constraints: Vec<Vec<usize>>,
+    mines_needed: Vec<usize>,
+    unassigned: Vec<usize>,
+    is_mine: Vec<bool>,
+    layouts: Vec<f64>,
+    mine_layouts: Vec<Vec<f64>>,
+    nodes_left: usize,
+}
+
+impl Search {
+    /// False when the search ran out of steps, the counts are incomplete then
+    fn run(&mut self, square: usize, mines: usize) -> bool {
+        let Some(nodes_left) = self.nodes_left.checked_sub(1) else {
+            return false;
+        };
+        self.nodes_left = nodes_left;
+
+        if square == self.is_mine.len() {
+            self.layouts[mines] += 1.;
+            for (i, &is_mine) in self.is_mine.iter().enumerate() {
+                if is_mine {
+                    self.mine_layouts[mines][i] += 1.;
+                }
+            }
+            return true;
+        }
+
+        for is_mine in [false, true] {
+            let fits = self.square_constraints[square].iter().all(|&c| {
+                let needed = self.mines_needed[c];
+                let unassigned = self.unassigned[c] - 1;
+                if is_mine {
+                    needed >= 1 && needed - 1 <= unassigned
+                } else {
+                    needed <= unassigned
+                }
+            });
+            if !fits {
+                continue;
+            }
+
+            for &c in &self.square_constraints[square] {
+                self.unassigned[c] -= 1;
+                if is_mine {
+                    self.mines_needed[c] -= 1;
+                }
+            }
+            self.is_mine[square] = is_mine;
+
+            if !self.run(square + 1, mines + is_mine as usize) {
+                return false;
+            }
+
+            self.is_mine[square] = false;
+            for &c in &self.square_constraints[square] {
+                self.unassigned[c] += 1;
+                if is_mine {
+                    self.mines_needed[c] += 1;
+                }
+            }
+        }
+        true
+    }
+}
+
+fn convolve(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut result = vec![0.; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            result[i + j] += x * y;
+        }
+    }
+    result
+}
+
+/// Natural logarithm of n choose k, negative infinity if k > n
+fn ln_choose(n: usize, k: usize) -> f64 {
+    if k > n {
+        return f64::NEG_INFINITY;
+    }
+
+    let k = k.min(n - k);
+    (0..k)
+        .map(|i| ((n - i) as f64).ln() - ((i + 1) as f64).ln())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game::FirstClick, topology::Topology};
+
+    #[test]
+    fn probabilities_add_up_to_the_mines_left() {
+        for seed in 0..50 {
+            let mut game =
+                Minesweeper::new_deferred_seeded(9, 9, 10, FirstClick::Opening, seed).unwrap();
+            game.click(4, 4).unwrap();
+
+            let probabilities = mine_probabilities(&game).unwrap();
+            let total = probabilities.iter().map(|&p| p as f64).sum::<f64>();
+            assert!((total - 10.).abs() < 1e-3, "seed {seed}: {total}");
+        }
+    }
+
+    #[test]
+    fn one_two_one() {
+        let game = "x.x\n121".parse::<Minesweeper>().unwrap();
+        let probabilities = mine_probabilities(&game).unwrap();
+        assert_eq!(probabilities, [1., 0., 1., 0., 0., 0.]);
+    }
+
+    #[test]
+    fn fewer_mines_than_opened_ones() {
+        let game = Minesweeper::from_parts(
+            vec![
+                vec![Square::Mine, Square::Nearby(1)],
+                vec![Square::Nearby(1); 2],
+            ],
+            vec![vec![true, false], vec![false; 2]],
+            vec![vec![false; 2]; 2],
+            0,
+            Topology::default(),
+            true,
+        );
+        assert_eq!(mine_probabilities(&game), None);
+    }
+
+    #[test]
+    fn gives_up_when_out_of_steps() {
+        let game = "x.x\n121".parse::<Minesweeper>().unwrap();
+        let constraints = constraints(&game);
+        let component = (0..constraints.len()).collect::<Vec<_>>();
+
+        assert!(count_layouts(component.clone(), &constraints, &mut 3).is_none());
+        let mut nodes_left = PROBABILITY_MAX_NODES;
+        let counted = count_layouts(component, &constraints, &mut nodes_left).unwrap();
+        assert_eq!(counted.layouts.iter().sum::<f64>(), 1.);
+    }
+}
@@ -0,0 +1,621 @@
+//! Recorded games that can be saved, shared and played back.
+//!
+//! A replay is a small text file. The first line is the format and its version, the second
+//! describes the board, and every line after that is a move:
+//!
+//! ```text
+//! minesweeper-replay 1
+//! 16 30 99 8127361 opening square - uniform
+//! 0 open 8 15
+//! 1520 flag 7 14
+//! 2210 chord 8 14
+//! ```
+//!
+//! The board line is `rows cols mines seed first-click topology rules layout`, where the first
+//! click policy is one of `none`, `safe`, `opening` or `corner`, the topology is written as in
+//! [`Topology`]'s `Display` and the rules are a comma separated list of [`Rule`]s, or `-` for
+//! none. The layout is a name read by [`layout::from_name`], or `mines` when the strategy has
+//! no name or the mines were not laid out from the seed, like on a board read from text, and
+//! then the next line lists every mine as `row,col`. Boards with a first click
+//! policy can end with the `row col` their mines were laid out around, when that was not the
+//! first move.
+//!
+//! Moves are `milliseconds kind row col`, with the time counted from the first move and kind
+//! being `open`, `flag`, `unflag` or `chord`.
+
+use std::{fmt::Display, path::Path, str::FromStr, sync::Arc, time::Duration};
+
+use crate::{
+    game::{FirstClick, Minesweeper, MinesweeperError, MoveKind, MoveOutcome},
+    layout::{self, Pattern},
+    rules::Rule,
+    topology::Topology,
+};
+
+pub const REPLAY_VERSION: u32 = 1;
+
+const HEADER: &str = "minesweeper-replay";
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    UnsupportedVersion(u32),
+    Malformed { line: usize, reason: String },
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "Could not read replay: {err}"),
+            ReplayError::UnsupportedVersion(version) => write!(
+                f,
+                "Replay version {version} is not supported, expected {REPLAY_VERSION}"
+            ),
+            ReplayError::Malformed { line, reason } => {
+                write!(f, "Malformed replay on line {line}: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(err: std::io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReplayMove {
+    pub at: Duration,
+    pub kind: MoveKind,
+    pub row: usize,
+    pub col: usize,
+}
+
+/// How the mines of a replay are laid out
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReplayLayout {
+    /// A strategy rebuilt with [`layout::from_name`], laying out the mines from the seed
+    Named(String),
+    /// Every mine on the board, for strategies that can not be rebuilt from a name and boards
+    /// whose mines were not laid out from the seed
+    Mines(Vec<(usize, usize)>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Replay {
+    pub rows: usize,
+    pub cols: usize,
+    pub mines: usize,
+    pub seed: u64,
+    pub first_click: Option<FirstClick>,
+    pub topology: Topology,
+    pub rules: Vec<Rule>,
+    pub layout: ReplayLayout,
+    /// Where the mines were laid out, if it was not the first move
+    pub laid_out_around: Option<(usize, usize)>,
+    pub moves: Vec<ReplayMove>,
+}
+
+impl Replay {
+    /// Records a game from its history, no matter if it was played by a person, a bot or the AI
+    pub fn from_game(game: &Minesweeper) -> Self {
+        let first_move = game
+            .history()
+            .first()
+            .map(|first_move| (first_move.row, first_move.col));
+
+        Replay {
+            rows: game.rows(),
+            cols: game.cols(),
+            mines: game.mines(),
+            seed: game.seed(),
+            first_click: game.first_click(),
+            topology: game.topology().clone(),
+            rules: game.rules().to_vec(),
+            layout: match game.layout().name().filter(|_| game.mines_from_seed()) {
+                Some(name) => ReplayLayout::Named(name),
+                None => ReplayLayout::Mines(
+                    (0..game.rows())
+                        .flat_map(|row| (0..game.cols()).map(move |col| (row, col)))
+                        .filter(|&(row, col)| game.is_mine(row, col))
+                        .collect(),
+                ),
+            },
+            laid_out_around: game
+                .laid_out_around()
+                .filter(|&square| Some(square) != first_move),
+            moves: game
+                .history()
+                .iter()
+                .map(|game_move| ReplayMove {
+                    at: game_move.at,
+                    kind: game_move.kind,
+                    row: game_move.row,
+                    col: game_move.col,
+                })
+                .collect(),
+        }
+    }
+
+    /// The board as it was before the first move. Boards that list their mines are created
+    /// with the mines in place.
+    pub fn new_game(&self) -> Result<Minesweeper, MinesweeperError> {
+        let layout = match &self.layout {
+            ReplayLayout::Named(name) => layout::from_name(name)
+                .map_err(|_| MinesweeperError::UnknownLayout(name.clone()))?,
+            ReplayLayout::Mines(mines) => {
+                let pattern = Pattern::from_mines(self.rows, self.cols, mines)?;
+                return Ok(
+                    Minesweeper::new_seeded(self.rows, self.cols, self.mines, self.seed)?
+                        .with_layout(Arc::new(pattern))?
+                        .with_topology(self.topology.clone())?
+                        .with_rules(self.rules.clone()),
+                );
+            }
+        };
+
+        match self.first_click {
+            Some(first_click) => {
+                let mut game = Minesweeper::new_deferred_seeded(
+                    self.rows,
+                    self.cols,
+                    self.mines,
+                    first_click,
+                    self.seed,
+                )?
+                .with_layout(layout)?
+                .with_topology(self.topology.clone())?
+                .with_rules(self.rules.clone());
+                if let Some((row, col)) = self.laid_out_around {
+                    game.lay_out_mines(row, col)?;
+                }
+                Ok(game)
+            }
+            None => Ok(
+                Minesweeper::new_seeded(self.rows, self.cols, self.mines, self.seed)?
+                    .with_layout(layout)?
+                    .with_topology(self.topology.clone())?
+                    .with_rules(self.rules.clone()),
+            ),
+        }
+    }
+
+    /// Plays the moves back one at a time, on the board from [`Replay::new_game`] that is
+    /// returned with it
+    pub fn playback(self) -> Result<(Playback, Minesweeper), MinesweeperError> {
+        let game = self.new_game()?;
+        Ok((
+            Playback {
+                replay: self,
+                next: 0,
+            },
+            game,
+        ))
+    }
+
+    /// The board after every move was made. Fails on the first move that could not be made.
+    pub fn play(&self) -> Result<Minesweeper, MinesweeperError> {
+        let (mut playback, mut game) = self.clone().playback()?;
+        while let Some(outcome) = playback.step(&mut game) {
+            outcome?;
+        }
+        Ok(game)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+}
+
+/// Steps through a replay one move at a time. The board is kept by the caller, so anything
+/// listening to its events sees the moves being made.
+#[derive(Debug, Clone)]
+pub struct Playback {
+    replay: Replay,
+    next: usize,
+}
+
+impl Playback {
+    /// When the next move was made, None when all moves are played
+    pub fn next_at(&self) -> Option<Duration> {
+        self.replay.moves.get(self.next).map(|next| next.at)
+    }
+
+    /// Makes the next move on `game`, the board that came with the playback. None when all
+    /// moves are played.
+    pub fn step(
+        &mut self,
+        game: &mut Minesweeper,
+    ) -> Option<Result<MoveOutcome, MinesweeperError>> {
+        let next = *self.replay.moves.get(self.next)?;
+        self.next += 1;
+
+        Some(match next.kind {
+            MoveKind::Open => game.click(next.row, next.col),
+            MoveKind::Chord => game.chord(next.row, next.col),
+            MoveKind::Flag | MoveKind::Unflag => game.mark(next.row, next.col),
+        })
+    }
+}
+
+fn first_click_name(first_click: Option<FirstClick>) -> &'static str {
+    match first_click {
+        None => "none",
+        Some(FirstClick::Safe) => "safe",
+        Some(FirstClick::Opening) => "opening",
+        Some(FirstClick::MoveToCorner) => "corner",
+    }
+}
+
+fn move_kind_name(kind: MoveKind) -> &'static str {
+    match kind {
+        MoveKind::Open => "open",
+        MoveKind::Flag => "flag",
+        MoveKind::Unflag => "unflag",
+        MoveKind::Chord => "chord",
+    }
+}
+
+impl Display for Replay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{HEADER} {REPLAY_VERSION}")?;
+        write!(
+            f,
+            "{} {} {} {} {} {} ",
+            self.rows,
+            self.cols,
+            self.mines,
+            self.seed,
+            first_click_name(self.first_click),
+            self.topology
+        )?;
+        if self.rules.is_empty() {
+            write!(f, "-")?;
+        } else {
+            let rules = self.rules.iter().map(Rule::to_string).collect::<Vec<_>>();
+            write!(f, "{}", rules.join(","))?;
+        }
+        match &self.layout {
+            ReplayLayout::Named(name) => write!(f, " {name}")?,
+            ReplayLayout::Mines(_) => write!(f, " mines")?,
+        }
+        match self.laid_out_around {
+            Some((row, col)) => writeln!(f, " {row} {col}")?,
+            None => writeln!(f)?,
+        }
+        if let ReplayLayout::Mines(mines) = &self.layout {
+            let mines = mines
+                .iter()
+                .map(|(row, col)| format!("{row},{col}"))
+                .collect::<Vec<_>>();
+            writeln!(f, "{}", mines.join(" "))?;
+        }
+
+        for replay_move in &self.moves {
+            writeln!(
+                f,
+                "{} {} {} {}",
+                replay_move.at.as_millis(),
+                move_kind_name(replay_move.kind),
+                replay_move.row,
+                replay_move.col
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Replay {
+    type Err = ReplayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().enumerate().map(|(i, line)| (i + 1, line));
+        let malformed = |line: usize, reason: &str| ReplayError::Malformed {
+            line,
+            reason: reason.to_string(),
+        };
+
+        let (line, header) = lines.next().ok_or_else(|| malformed(1, "empty replay"))?;
+        let version = match header.split_whitespace().collect::<Vec<_>>()[..] {
+            [HEADER, version] => version
+                .parse::<u32>()
+                .map_err(|_| malformed(line, "version is not a number"))?,
+            _ => return Err(malformed(line, "not a minesweeper replay")),
+        };
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        let (line, board) = lines
+            .next()
+            .ok_or_else(|| malformed(2, "missing board line"))?;
+        let mut board = board.split_whitespace().collect::<Vec<_>>();
+        if board.len() < 8 {
+            return Err(malformed(
+                line,
+                "expected rows cols mines seed first-click topology rules layout",
+            ));
+        }
+        let topology = board
+            .remove(5)
+            .parse::<Topology>()
+            .map_err(|reason| malformed(line, &reason))?;
+        let rules = match board.remove(5) {
+            "-" => Vec::new(),
+            rules => rules
+                .split(',')
+                .map(str::parse::<Rule>)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|reason| malformed(line, &reason))?,
+        };
+        let layout = match board.remove(5) {
+            "mines" => ReplayLayout::Mines(Vec::new()),
+            name => {
+                layout::from_name(name).map_err(|reason| malformed(line, &reason))?;
+                ReplayLayout::Named(name.to_string())
+            }
+        };
+        let (rows, cols, mines, seed, first_click, laid_out_around) = match board[..] {
+            [rows, cols, mines, seed, first_click, ref laid_out_around @ ..] => (
+                parse_number(rows, line, "rows")?,
+                parse_number(cols, line, "cols")?,
+                parse_number(mines, line, "mines")?,
+                parse_number(seed, line, "seed")?,
+                match first_click {
+                    "none" => None,
+                    "safe" => Some(FirstClick::Safe),
+                    "opening" => Some(FirstClick::Opening),
+                    "corner" => Some(FirstClick::MoveToCorner),
+                    _ => return Err(malformed(line, "unknown first click policy")),
+                },
+                match laid_out_around {
+                    [] => None,
+                    [row, col] => Some((
+                        parse_number(row, line, "row")?,
+                        parse_number(col, line, "col")?,
+                    )),
+                    _ => return Err(malformed(line, "expected row col at the end")),
+                },
+            ),
+            _ => return Err(malformed(line, "expected rows cols mines seed first-click")),
+        };
+        if rows == 0 || cols == 0 || mines >= rows.saturating_mul(cols) {
+            return Err(malformed(line, "board does not fit its mines"));
+        }
+        if !topology.fits(rows as usize, cols as usize) {
+            return Err(malformed(line, "board is too small to wrap around"));
+        }
+        if laid_out_around.is_some_and(|(row, col)| row >= rows || col >= cols)
+            || laid_out_around.is_some() && first_click.is_none()
+        {
+            return Err(malformed(
+                line,
+                "mines are laid out outside a deferred board",
+            ));
+        }
+
+        let layout = match layout {
+            ReplayLayout::Mines(_) => {
+                let (line, mines_line) = lines
+                    .next()
+                    .ok_or_else(|| malformed(line + 1, "missing mines line"))?;
+                let mut positions = Vec::new();
+                for position in mines_line.split_whitespace() {
+                    let (row, col) = position
+                        .split_once(',')
+                        .ok_or_else(|| malformed(line, "expected row,col"))?;
+                    let (row, col) = (
+                        parse_number(row, line, "row")?,
+                        parse_number(col, line, "col")?,
+                    );
+                    if row >= rows || col >= cols {
+                        return Err(malformed(line, "mine is outside the board"));
+                    }
+                    positions.push((row as usize, col as usize));
+                }
+
+                let mut distinct = positions.clone();
+                distinct.sort_unstable();
+                distinct.dedup();
+                if distinct.len() as u64 != mines || positions.len() as u64 != mines {
+                    return Err(malformed(line, "mines do not match the mine count"));
+                }
+                ReplayLayout::Mines(positions)
+            }
+            named => named,
+        };
+
+        let mut moves = Vec::new();
+        for (line, replay_move) in lines {
+            if replay_move.trim().is_empty() {
+                continue;
+            }
+
+            let replay_move = match replay_move.split_whitespace().collect::<Vec<_>>()[..] {
+                [at, kind, row, col] => ReplayMove {
+                    at: Duration::from_millis(parse_number(at, line, "time")?),
+                    kind: match kind {
+                        "open" => MoveKind::Open,
+                        "flag" => MoveKind::Flag,
+                        "unflag" => MoveKind::Unflag,
+                        "chord" => MoveKind::Chord,
+                        _ => return Err(malformed(line, "unknown move")),
+                    },
+                    row: parse_number(row, line, "row")? as usize,
+                    col: parse_number(col, line, "col")? as usize,
+                },
+                _ => return Err(malformed(line, "expected time kind row col")),
+            };
+            if replay_move.row as u64 >= rows || replay_move.col as u64 >= cols {
+                return Err(malformed(line, "move is outside the board"));
+            }
+
+            moves.push(replay_move);
+        }
+
+        Ok(Replay {
+            rows: rows as usize,
+            cols: cols as usize,
+            mines: mines as usize,
+            seed,
+            first_click,
+            topology,
+            rules,
+            layout,
+            laid_out_around: laid_out_around.map(|(row, col)| (row as usize, col as usize)),
+            moves,
+        })
+    }
+}
+
+fn parse_number(s: &str, line: usize, what: &str) -> Result<u64, ReplayError> {
+    s.parse().map_err(|_| ReplayError::Malformed {
+        line,
+        reason: format!("{what} is not a number"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::game::{GameEvent, GameStatus};
+
+    /// Records a game, writes the replay as text and reads it back
+    fn replayed(game: &Minesweeper) -> Minesweeper {
+        let replay = Replay::from_game(game)
+            .to_string()
+            .parse::<Replay>()
+            .unwrap();
+        replay.play().unwrap()
+    }
+
+    #[test]
+    fn boards_not_laid_out_from_the_seed_list_their_mines() {
+        let mut from_layout = Minesweeper::from_mine_layout(&[
+            vec![true, false, false, false],
+            vec![false, false, false, false],
+            vec![false, false, false, true],
+        ])
+        .unwrap();
+        let mut from_text = "x...\n....\n...x".parse::<Minesweeper>().unwrap();
+
+        for game in [&mut from_layout, &mut from_text] {
+            assert!(!game.mines_from_seed());
+            game.click(2, 0).unwrap();
+            assert!(matches!(
+                Replay::from_game(game).layout,
+                ReplayLayout::Mines(_)
+            ));
+            let replayed = replayed(game);
+            assert_eq!(format!("{replayed:#}"), format!("{game:#}"));
+            assert_eq!(replayed.status(), game.status());
+        }
+    }
+
+    #[test]
+    fn playback_is_heard_by_listeners_of_the_board() {
+        let mut game = Minesweeper::new_seeded(9, 9, 10, 4).unwrap();
+        for (row, col) in (0..9).flat_map(|row| (0..9).map(move |col| (row, col))) {
+            if game.status() != GameStatus::Playing {
+                break;
+            }
+            let _ = game.click(row, col);
+        }
+
+        let (mut playback, mut replayed) = Replay::from_game(&game).playback().unwrap();
+        let events = replayed.events();
+        while let Some(outcome) = playback.step(&mut replayed) {
+            outcome.unwrap();
+        }
+
+        assert_eq!(replayed.status(), GameStatus::Lost);
+        assert_eq!(events.try_iter().last(), Some(GameEvent::Lost));
+    }
+
+    #[test]
+    fn replays_play_back_the_same_game() {
+        let boards = [
+            (Some(FirstClick::Safe), "square", "", "uniform"),
+            (
+                Some(FirstClick::Opening),
+                "knight+wrap",
+                "liar,lives:2",
+                "clustered:3:2",
+            ),
+            (
+                Some(FirstClick::MoveToCorner),
+                "hex",
+                "double:3,orthogonal,flags:5",
+                "uniform",
+            ),
+            (None, "cross", "", "relocating:uniform"),
+        ];
+
+        for (i, (first_click, topology, rules, layout)) in boards.into_iter().enumerate() {
+            for seed in 0..10 {
+                let (rows, cols, mines) = (8, 10, 15);
+                let game = match first_click {
+                    Some(first_click) => {
+                        Minesweeper::new_deferred_seeded(rows, cols, mines, first_click, seed)
+                    }
+                    None => Minesweeper::new_seeded(rows, cols, mines, seed),
+                };
+                let rules = rules
+                    .split(',')
+                    .filter(|rule| !rule.is_empty())
+                    .map(|rule| rule.parse().unwrap())
+                    .collect();
+                let mut game = game
+                    .unwrap()
+                    .with_layout(layout::from_name(layout).unwrap())
+                    .unwrap()
+                    .with_topology(topology.parse().unwrap())
+                    .unwrap()
+                    .with_rules(rules);
+                if seed % 2 == 1 {
+                    game.lay_out_mines(3, 4).unwrap();
+                }
+
+                let mut rng = SmallRng::seed_from_u64(seed);
+                for _ in 0..80 {
+                    let (row, col) = (rng.random_range(0..rows), rng.random_range(0..cols));
+                    let _ = match rng.random_range(0..10) {
+                        0..6 => game.click(row, col),
+                        6..8 => game.mark(row, col),
+                        _ => game.chord(row, col),
+                    };
+                }
+
+                let replay = Replay::from_game(&game);
+                let replayed = replayed(&game);
+                assert_eq!(
+                    format!("{replayed:#}"),
+                    format!("{game:#}"),
+                    "board {i}, seed {seed}"
+                );
+                assert_eq!(replayed.status(), game.status());
+                let moves = |replay: Replay| {
+                    replay
+                        .moves
+                        .into_iter()
+                        .map(|replay_move| (replay_move.kind, replay_move.row, replay_move.col))
+                        .collect::<Vec<_>>()
+                };
+                assert_eq!(moves(Replay::from_game(&replayed)), moves(replay));
+            }
+        }
+    }
+}
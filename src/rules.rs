@@ -0,0 +1,173 @@
+//! Extra rules that turn a board into a puzzle variant.
+//!
+//! Rules are given to [`Minesweeper::with_rules`](crate::game::Minesweeper::with_rules) and
+//! can be combined freely. They change how numbers are counted, what a move does, and what the
+//! solver can deduce from a number. The board and the solver only ask a list of rules through
+//! [`RuleSet`], so what a rule does is all written down here.
+
+use std::{fmt::Display, str::FromStr};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Rule {
+    /// Every number is one more or one less than the mines around it
+    Liar,
+    /// This many of the mines are double mines, counting as two in the numbers around them
+    DoubleMines(usize),
+    /// Numbers only count the neighbours in the same row or column
+    Orthogonal,
+    /// No more than this many flags can be placed
+    FlagCap(usize),
+    /// This many mines can be hit before the game is lost
+    Lives(u32),
+}
+
+impl Rule {
+    /// True for rules that make a number differ from the mines around it
+    pub fn changes_numbers(&self) -> bool {
+        matches!(self, Rule::Liar | Rule::DoubleMines(_))
+    }
+}
+
+/// What a list of rules means for a board
+pub trait RuleSet {
+    /// Numbers only count the neighbours in the same row or column
+    fn counts_orthogonally(&self) -> bool;
+
+    /// Numbers are one more or one less than the mines around them
+    fn has_lies(&self) -> bool;
+
+    /// How many of the mines count twice
+    fn doubled_mines(&self) -> usize;
+
+    /// True when every number is exactly the mines around it, as on a classic board
+    fn has_exact_numbers(&self) -> bool;
+
+    /// How many mines can be hit before the game is lost
+    fn lives(&self) -> u32;
+
+    /// How many flags can be placed, None without a cap
+    fn flag_cap(&self) -> Option<usize>;
+
+    /// The number shown on a square that counts `count`, where `one_more` picks which way a
+    /// liar's number is off
+    fn shown_number(&self, count: u32, one_more: bool) -> u32;
+
+    /// How many of the squares around a number `n` can be mines. A liar's number is one off,
+    /// and with double mines every mine can count once or twice.
+    fn mine_bounds(&self, n: usize) -> (usize, usize);
+
+    /// The most a number `n` can count, with double mines counting twice
+    fn most_counted(&self, n: usize) -> usize;
+}
+
+impl RuleSet for [Rule] {
+    fn counts_orthogonally(&self) -> bool {
+        self.contains(&Rule::Orthogonal)
+    }
+
+    fn has_lies(&self) -> bool {
+        self.contains(&Rule::Liar)
+    }
+
+    fn doubled_mines(&self) -> usize {
+        self.iter()
+            .find_map(|rule| match rule {
+                Rule::DoubleMines(amount) => Some(*amount),
+                _ => None,
+            })
+            .unwrap_or(0)
+    }
+
+    fn has_exact_numbers(&self) -> bool {
+        !self.iter().any(Rule::changes_numbers)
+    }
+
+    fn lives(&self) -> u32 {
+        self.iter()
+            .find_map(|rule| match rule {
+                Rule::Lives(lives) => Some(*lives),
+                _ => None,
+            })
+            .unwrap_or(0)
+    }
+
+    fn flag_cap(&self) -> Option<usize> {
+        self.iter().find_map(|rule| match rule {
+            Rule::FlagCap(cap) => Some(*cap),
+            _ => None,
+        })
+    }
+
+    fn shown_number(&self, count: u32, one_more: bool) -> u32 {
+        match (self.has_lies(), one_more) {
+            (false, _) => count,
+            (true, true) => count + 1,
+            (true, false) => count.saturating_sub(1),
+        }
+    }
+
+    fn mine_bounds(&self, n: usize) -> (usize, usize) {
+        let least_counted = match (self.has_lies(), n) {
+            (true, 0) => 1,
+            (true, n) => n - 1,
+            (false, n) => n,
+        };
+        // Every mine counts at least once, so the most counted is also the most mines
+        if self.doubled_mines() > 0 {
+            (least_counted.div_ceil(2), self.most_counted(n))
+        } else {
+            (least_counted, self.most_counted(n))
+        }
+    }
+
+    fn most_counted(&self, n: usize) -> usize {
+        match (self.has_lies(), n) {
+            (true, 0) => 1,
+            (true, n) => n + 1,
+            (false, n) => n,
+        }
+    }
+}
+
+/// Written as `liar`, `double:N`, `orthogonal`, `flags:N` or `lives:N`
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rule::Liar => write!(f, "liar"),
+            Rule::DoubleMines(mines) => write!(f, "double:{mines}"),
+            Rule::Orthogonal => write!(f, "orthogonal"),
+            Rule::FlagCap(flags) => write!(f, "flags:{flags}"),
+            Rule::Lives(lives) => write!(f, "lives:{lives}"),
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, amount) = match s.split_once(':') {
+            Some((name, amount)) => (
+                name,
+                Some(
+                    amount
+                        .parse::<usize>()
+                        .map_err(|_| format!("Invalid amount in rule {s:?}"))?,
+                ),
+            ),
+            None => (s, None),
+        };
+
+        match (name, amount) {
+            ("liar", None) => Ok(Rule::Liar),
+            ("double", Some(mines)) => Ok(Rule::DoubleMines(mines)),
+            ("orthogonal", None) => Ok(Rule::Orthogonal),
+            ("flags", Some(flags)) => Ok(Rule::FlagCap(flags)),
+            ("lives", Some(lives)) => u32::try_from(lives)
+                .map(Rule::Lives)
+                .map_err(|_| format!("Too many lives in rule {s:?}")),
+            _ => Err(format!("Unknown rule {s:?}")),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! The settings overlay, where the board for new games is picked.
+
+use nannou::prelude::*;
+
+use crate::{config::Preset, game::Difficulty, skin::Skin};
+
+/// Largest number of rows or columns of a custom board
+const MAX_SIDE: usize = 200;
+
+const LINE_HEIGHT: f32 = 50.;
+const LINE_WIDTH: f32 = 500.;
+
+/// The lines of the menu, top to bottom
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Line {
+    Standard(Difficulty),
+    Rows,
+    Cols,
+    Mines,
+}
+
+const LINES: [Line; 6] = [
+    Line::Standard(Difficulty::Beginner),
+    Line::Standard(Difficulty::Intermediate),
+    Line::Standard(Difficulty::Expert),
+    Line::Rows,
+    Line::Cols,
+    Line::Mines,
+];
+
+/// What the player did in the menu
+pub enum MenuAction {
+    None,
+    Close,
+    Pick(Preset),
+}
+
+pub struct SettingsMenu {
+    selected: usize,
+    rows: usize,
+    cols: usize,
+    mines: usize,
+}
+
+impl SettingsMenu {
+    /// Opens the menu on the preset that is played now
+    pub fn new(current: Preset) -> Self {
+        let (rows, cols, mines) = current.dimensions();
+        let selected = LINES
+            .iter()
+            .position(|line| matches!(current, Preset::Standard(d) if *line == Line::Standard(d)))
+            // Custom boards open on their rows
+            .unwrap_or(3);
+
+        Self {
+            selected,
+            rows: rows.min(MAX_SIDE),
+            cols: cols.min(MAX_SIDE),
+            mines: mines.min(rows.min(MAX_SIDE) * cols.min(MAX_SIDE) - 1),
+        }
+    }
+
+    /// Up and down choose a line, left and right change the custom board, ten at a time with
+    /// shift held, and enter starts a game with the chosen preset
+    pub fn key(&mut self, key: Key, shift: bool) -> MenuAction {
+        let step = if shift { 10 } else { 1 };
+        match key {
+            Key::Up => self.selected = self.selected.saturating_sub(1),
+            Key::Down => self.selected = (self.selected + 1).min(LINES.len() - 1),
+            Key::Left => self.change(|n| n.saturating_sub(step)),
+            Key::Right => self.change(|n| n + step),
+            Key::Return | Key::NumpadEnter => return MenuAction::Pick(self.preset()),
+            Key::Escape => return MenuAction::Close,
+            _ => {}
+        }
+        MenuAction::None
+    }
+
+    /// Clicking a line selects it, and clicking a standard preset picks it right away
+    pub fn click(&mut self, x: f32, y: f32) -> MenuAction {
+        let Some(i) = (0..LINES.len()).find(|&i| {
+            let (line_x, line_y) = line_x_y(i);
+            (x - line_x).abs() <= LINE_WIDTH / 2. && (y - line_y).abs() <= LINE_HEIGHT / 2.
+        }) else {
+            return MenuAction::None;
+        };
+
+        self.selected = i;
+        match LINES[i] {
+            Line::Standard(_) => MenuAction::Pick(self.preset()),
+            _ => MenuAction::None,
+        }
+    }
+
+    fn preset(&self) -> Preset {
+        match LINES[self.selected] {
+            Line::Standard(difficulty) => Preset::Standard(difficulty),
+            Line::Rows | Line::Cols | Line::Mines => Preset::Custom {
+                rows: self.rows,
+                cols: self.cols,
+                mines: self.mines,
+            },
+        }
+    }
+
+    /// Changes the selected number of the custom board, keeping room for at least one square
+    /// without a mine
+    fn change(&mut self, f: impl Fn(usize) -> usize) {
+        match LINES[self.selected] {
+            Line::Standard(_) => return,
+            Line::Rows => self.rows = f(self.rows).clamp(1, MAX_SIDE),
+            Line::Cols => self.cols = f(self.cols).clamp(1, MAX_SIDE),
+            Line::Mines => self.mines = f(self.mines),
+        }
+        self.mines = self.mines.min(self.rows * self.cols - 1);
+    }
+
+    pub fn draw(&self, draw: &Draw, skin: &Skin, width: f32, height: f32) {
+        draw.rect()
+            .w_h(width, height)
+            .color(Rgba::new(0., 0., 0., 0.8));
+        skin.text(draw, "Settings")
+            .x_y(0., line_x_y(0).1 + LINE_HEIGHT * 1.5)
+            .w(LINE_WIDTH)
+            .font_size(36)
+            .color(WHITE);
+
+        for (i, line) in LINES.iter().enumerate() {
+            let text = match line {
+                Line::Standard(difficulty) => {
+                    let (rows, cols, mines) = difficulty.dimensions();
+                    format!("{difficulty:?}: {rows}x{cols}, {mines} mines")
+                }
+                Line::Rows => format!("Custom rows: < {} >", self.rows),
+                Line::Cols => format!("Custom columns: < {} >", self.cols),
+                Line::Mines => format!("Custom mines: < {} >", self.mines),
+            };
+            let (x, y) = line_x_y(i);
+            if i == self.selected {
+                draw.rect()
+                    .w_h(LINE_WIDTH, LINE_HEIGHT - 4.)
+                    .x_y(x, y)
+                    .color(Rgb::new(0.3, 0.3, 0.3));
+            }
+            skin.text(draw, &text)
+                .x_y(x, y)
+                .w(LINE_WIDTH)
+                .font_size(24)
+                .color(WHITE);
+        }
+
+        skin.text(
+            draw,
+            "Up/Down: choose   Left/Right: change   Enter: start   Esc: close",
+        )
+        .x_y(0., line_x_y(LINES.len()).1 - LINE_HEIGHT / 2.)
+        .w(LINE_WIDTH * 1.5)
+        .font_size(16)
+        .color(LIGHTGRAY);
+    }
+}
+
+/// The centre of a line of the menu
+fn line_x_y(i: usize) -> (f32, f32) {
+    (0., LINE_HEIGHT * (LINES.len() as f32 / 2. - 0.5 - i as f32))
+}
@@ -0,0 +1,71 @@
+//! Themes ready to be drawn, with their textures and font loaded.
+
+use std::collections::HashMap;
+
+use nannou::{
+    draw::{primitive::Text, Drawing},
+    prelude::*,
+    text::{font, Font},
+};
+
+use crate::theme::{Colour, Theme, TEXTURES};
+
+pub struct Skin {
+    pub theme: Theme,
+    textures: HashMap<&'static str, wgpu::Texture>,
+    font: Option<Font>,
+}
+
+impl Skin {
+    /// Loads the textures and font the theme has, the ones that can not be read are left out
+    pub fn load(app: &App, theme: Theme) -> Self {
+        let textures = TEXTURES
+            .into_iter()
+            .filter_map(|name| {
+                let path = theme.file(&format!("{name}.png"))?;
+                match wgpu::Texture::from_path(app, &path) {
+                    Ok(texture) => Some((name, texture)),
+                    Err(err) => {
+                        println!("Could not load {}: {err}", path.display());
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        let font = theme
+            .font
+            .as_ref()
+            .and_then(|name| theme.file(name))
+            .and_then(|path| match font::from_file(&path) {
+                Ok(font) => Some(font),
+                Err(err) => {
+                    println!("Could not load {}: {err}", path.display());
+                    None
+                }
+            });
+
+        Skin {
+            theme,
+            textures,
+            font,
+        }
+    }
+
+    pub fn texture(&self, name: &str) -> Option<&wgpu::Texture> {
+        self.textures.get(name)
+    }
+
+    /// Text in the theme's font
+    pub fn text<'a>(&self, draw: &'a Draw, text: &str) -> Drawing<'a, Text> {
+        let drawing = draw.text(text);
+        match &self.font {
+            Some(font) => drawing.font(font.clone()),
+            None => drawing,
+        }
+    }
+}
+
+pub fn rgb(colour: Colour) -> Rgb {
+    Rgb::new(colour.0, colour.1, colour.2)
+}
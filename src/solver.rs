@@ -0,0 +1,415 @@
+//! Finds squares that are certainly safe or certainly mines from what is visible on the board.
+//!
+//! Flags are ignored, as they might be wrong. Numbers are read following the board's
+//! [rules](crate::rules): a liar's number or one next to double mines only bounds how many of
+//! the squares around it are mines, and every rule works from those bounds.
+
+use crate::{
+    game::{Minesweeper, MoveOutcome, Square},
+    rules::RuleSet,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeductionKind {
+    Safe,
+    Mine,
+}
+
+/// The rule a deduction was made with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// A number that is already satisfied, or that has as many unknown squares as missing mines
+    SingleNumber,
+    /// Two numbers sharing squares, where one needs more mines than the squares only it touches
+    /// and the shared ones can hold. This covers both subsets and overlaps.
+    NumberPair,
+    /// The total amount of mines left on the board
+    MineCount,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Deduction {
+    pub square: (usize, usize),
+    pub kind: DeductionKind,
+    pub reason: Reason,
+    /// The opened numbers the deduction follows from, together with the deductions before it
+    pub because: Vec<(usize, usize)>,
+}
+
+/// A number on the board: between `min` and `max` of the unknown `squares` around it are
+/// mines. Both are the same on a classic board.
+#[derive(Clone, Debug)]
+struct Constraint {
+    number: (usize, usize),
+    squares: Vec<(usize, usize)>,
+    min: usize,
+    max: usize,
+}
+
+/// What is known about every square so far
+struct Knowledge {
+    mines: Vec<Vec<bool>>,
+    safe: Vec<Vec<bool>>,
+}
+
+impl Knowledge {
+    fn is_unknown(&self, game: &Minesweeper, row: usize, col: usize) -> bool {
+        !game.is_square_open(row, col) && !self.mines[row][col] && !self.safe[row][col]
+    }
+
+    /// Deduced mines, and mines that were opened while there were lives left
+    fn is_mine(&self, game: &Minesweeper, row: usize, col: usize) -> bool {
+        self.mines[row][col]
+            || game.is_square_open(row, col) && matches!(game.shown(row, col), Square::Mine)
+    }
+}
+
+/// Everything that can be deduced from the visible state of `game`, in the order it was found.
+/// Later deductions may build on earlier ones.
+pub fn deduce(game: &Minesweeper) -> Vec<Deduction> {
+    let mut knowledge = Knowledge {
+        mines: vec![vec![false; game.cols()]; game.rows()],
+        safe: vec![vec![false; game.cols()]; game.rows()],
+    };
+    let mut deductions = Vec::new();
+
+    loop {
+        let found = deduce_step(game, &knowledge);
+        if found.is_empty() {
+            break;
+        }
+
+        for deduction in found {
+            let (row, col) = deduction.square;
+            if !knowledge.is_unknown(game, row, col) {
+                continue;
+            }
+
+            match deduction.kind {
+                DeductionKind::Safe => knowledge.safe[row][col] = true,
+                DeductionKind::Mine => knowledge.mines[row][col] = true,
+            }
+            deductions.push(deduction);
+        }
+    }
+
+    deductions
+}
+
+/// The first closed square that is certainly safe
+pub fn hint(game: &Minesweeper) -> Option<Deduction> {
+    deduce(game)
+        .into_iter()
+        .find(|deduction| deduction.kind == DeductionKind::Safe)
+}
+
+/// Opens `(row, col)` on a copy of the board and keeps opening squares that are certainly
+/// safe. True if that clears the whole board, so it can be solved without guessing.
+pub fn is_solvable(game: &Minesweeper, row: usize, col: usize) -> bool {
+    let mut game = game.clone();
+
+    if game.click(row, col).is_err() {
+        return false;
+    }
+
+    loop {
+        let safe = deduce(&game)
+            .into_iter()
+            .filter(|deduction| deduction.kind == DeductionKind::Safe)
+            .collect::<Vec<_>>();
+        if safe.is_empty() {
+            break;
+        }
+
+        for deduction in safe {
+            let (safe_row, safe_col) = deduction.square;
+            // A click that fails or finds a mine means the deduction was wrong, so the board
+            // can't be solved from here. Going on would deduce the same squares forever.
+            match game.click(safe_row, safe_col) {
+                Ok(MoveOutcome::HitMine(..) | MoveOutcome::LostLife(..)) | Err(_) => return false,
+                Ok(_) => {}
+            }
+        }
+    }
+
+    game.is_board_completed()
+}
+
+/// Tries the rules from the simplest to the most expensive, stopping at the first one that
+/// finds something
+fn deduce_step(game: &Minesweeper, knowledge: &Knowledge) -> Vec<Deduction> {
+    let constraints = constraints(game, knowledge);
+
+    let found = single_number(&constraints);
+    if !found.is_empty() {
+        return found;
+    }
+
+    let found = number_pairs(&constraints);
+    if !found.is_empty() {
+        return found;
+    }
+
+    mine_count(game, knowledge, &constraints)
+}
+
+fn single_number(constraints: &[Constraint]) -> Vec<Deduction> {
+    let mut found = Vec::new();
+
+    for constraint in constraints {
+        let kind = if constraint.max == 0 {
+            DeductionKind::Safe
+        } else if constraint.min == constraint.squares.len() {
+            DeductionKind::Mine
+        } else {
+            continue;
+        };
+
+        found.extend(constraint.squares.iter().map(|&square| Deduction {
+            square,
+            kind,
+            reason: Reason::SingleNumber,
+            because: vec![constraint.number],
+        }));
+    }
+
+    found
+}
+
+fn number_pairs(constraints: &[Constraint]) -> Vec<Deduction> {
+    let mut found = Vec::new();
+
+    for a in constraints {
+        for b in constraints {
+            if a.number == b.number || b.min < a.max {
+                continue;
+            }
+            if !a.squares.iter().any(|square| b.squares.contains(square)) {
+                continue;
+            }
+
+            let only_a = a
+                .squares
+                .iter()
+                .filter(|square| !b.squares.contains(square));
+            let only_b = b
+                .squares
+                .iter()
+                .filter(|square| !a.squares.contains(square))
+                .collect::<Vec<_>>();
+
+            // The shared squares hold at most `a.max`, so if the squares only `b` touches
+            // are needed to reach `b.min` they are all mines and `a` is satisfied by the
+            // shared ones.
+            if b.min - a.max != only_b.len() {
+                continue;
+            }
+
+            let because = vec![a.number, b.number];
+            found.extend(only_b.into_iter().map(|&square| Deduction {
+                square,
+                kind: DeductionKind::Mine,
+                reason: Reason::NumberPair,
+                because: because.clone(),
+            }));
+            found.extend(only_a.map(|&square| Deduction {
+                square,
+                kind: DeductionKind::Safe,
+                reason: Reason::NumberPair,
+                because: because.clone(),
+            }));
+        }
+    }
+
+    found
+}
+
+fn mine_count(
+    game: &Minesweeper,
+    knowledge: &Knowledge,
+    constraints: &[Constraint],
+) -> Vec<Deduction> {
+    let unknown = (0..game.rows())
+        .flat_map(|row| (0..game.cols()).map(move |col| (row, col)))
+        .filter(|&(row, col)| knowledge.is_unknown(game, row, col))
+        .collect::<Vec<_>>();
+    let known_mines = (0..game.rows())
+        .flat_map(|row| (0..game.cols()).map(move |col| (row, col)))
+        .filter(|&(row, col)| knowledge.is_mine(game, row, col))
+        .count();
+    let mines_left = game.mines().saturating_sub(known_mines);
+
+    let deduction = |square, kind, because: &Vec<(usize, usize)>| Deduction {
+        square,
+        kind,
+        reason: Reason::MineCount,
+        because: because.clone(),
+    };
+
+    if mines_left == 0 {
+        return unknown
+            .into_iter()
+            .map(|square| deduction(square, DeductionKind::Safe, &Vec::new()))
+            .collect();
+    }
+    if mines_left == unknown.len() {
+        return unknown
+            .into_iter()
+            .map(|square| deduction(square, DeductionKind::Mine, &Vec::new()))
+            .collect();
+    }
+
+    // Numbers that share no squares need at least the sum of their mines. If that is all
+    // that is left, every square outside them is safe.
+    let mut covered = Vec::new();
+    let mut because = Vec::new();
+    let mut mines_needed = 0;
+    for constraint in constraints {
+        if constraint
+            .squares
+            .iter()
+            .any(|square| covered.contains(square))
+        {
+            continue;
+        }
+
+        covered.extend(constraint.squares.iter().copied());
+        because.push(constraint.number);
+        mines_needed += constraint.min;
+    }
+
+    if mines_needed != mines_left {
+        return Vec::new();
+    }
+
+    unknown
+        .into_iter()
+        .filter(|square| !covered.contains(square))
+        .map(|square| deduction(square, DeductionKind::Safe, &because))
+        .collect()
+}
+
+/// One constraint for every opened number, only counting squares that are still unknown
+fn constraints(game: &Minesweeper, knowledge: &Knowledge) -> Vec<Constraint> {
+    let mut constraints = Vec::new();
+
+    for row in 0..game.rows() {
+        for col in 0..game.cols() {
+            let n = match game.shown(row, col) {
+                Square::Empty => 0,
+                Square::Nearby(n) => n as usize,
+                Square::Mine => continue,
+            };
+            if !game.is_square_open(row, col) {
+                continue;
+            }
+
+            let mut squares = Vec::new();
+            let mut known_mines = 0;
+            for (n_row, n_col) in game.counted_neighbours(row, col) {
+                if knowledge.is_mine(game, n_row, n_col) {
+                    known_mines += 1;
+                } else if knowledge.is_unknown(game, n_row, n_col) {
+                    squares.push((n_row, n_col));
+                }
+            }
+
+            if !squares.is_empty() {
+                let (min, max) = game.rules().mine_bounds(n);
+                constraints.push(Constraint {
+                    number: (row, col),
+                    squares,
+                    min: min.saturating_sub(known_mines),
+                    max: max.saturating_sub(known_mines),
+                });
+            }
+        }
+    }
+
+    constraints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every deduction on a full text board, checked against its mines
+    fn deduce_text(board: &str) -> Vec<Deduction> {
+        let game = board.parse::<Minesweeper>().unwrap();
+        let deductions = deduce(&game);
+        for deduction in &deductions {
+            let (row, col) = deduction.square;
+            assert_eq!(
+                deduction.kind == DeductionKind::Mine,
+                game.is_mine(row, col),
+                "{deduction:?}"
+            );
+        }
+        deductions
+    }
+
+    fn found(deductions: &[Deduction], square: (usize, usize)) -> Option<(DeductionKind, Reason)> {
+        deductions
+            .iter()
+            .find(|deduction| deduction.square == square)
+            .map(|deduction| (deduction.kind, deduction.reason))
+    }
+
+    #[test]
+    fn single_numbers() {
+        let deductions = deduce_text("x1.\n11.");
+        assert_eq!(
+            found(&deductions, (0, 0)),
+            Some((DeductionKind::Mine, Reason::SingleNumber))
+        );
+        assert_eq!(
+            found(&deductions, (0, 2)),
+            Some((DeductionKind::Safe, Reason::SingleNumber))
+        );
+        assert_eq!(
+            found(&deductions, (1, 2)),
+            Some((DeductionKind::Safe, Reason::SingleNumber))
+        );
+        assert_eq!(deductions.len(), 3);
+    }
+
+    #[test]
+    fn one_two_one() {
+        let deductions = deduce_text("x.x\n121");
+        assert_eq!(
+            deductions.first().map(|deduction| deduction.reason),
+            Some(Reason::NumberPair)
+        );
+        assert_eq!(found(&deductions, (0, 0)).unwrap().0, DeductionKind::Mine);
+        assert_eq!(found(&deductions, (0, 1)).unwrap().0, DeductionKind::Safe);
+        assert_eq!(found(&deductions, (0, 2)).unwrap().0, DeductionKind::Mine);
+    }
+
+    #[test]
+    fn mine_count() {
+        let deductions = deduce_text("1x...");
+        assert_eq!(
+            found(&deductions, (0, 1)),
+            Some((DeductionKind::Mine, Reason::SingleNumber))
+        );
+        for col in 2..5 {
+            assert_eq!(
+                found(&deductions, (0, col)),
+                Some((DeductionKind::Safe, Reason::MineCount))
+            );
+        }
+    }
+
+    #[test]
+    fn a_safe_square_that_can_not_be_clicked_ends_the_search() {
+        // The flag on (0, 2) is wrong, so it is deduced safe but clicking it fails
+        let game = ".xf..".parse::<Minesweeper>().unwrap();
+        assert!(!is_solvable(&game, 0, 0));
+    }
+
+    #[test]
+    fn nothing_to_deduce_from_a_coin_flip() {
+        assert!(deduce_text("1x\n1.").is_empty());
+        assert_eq!(hint(&"1x\n1.".parse().unwrap()), None);
+    }
+}
@@ -0,0 +1,518 @@
+//! Reading and writing boards as text.
+//!
+//! Boards are written one row per line, one character per square. There are two flavours,
+//! both read by `str::parse::<Minesweeper>()`:
+//!
+//! The visible board is what `format!("{}", game)` prints, it shows what a player sees:
+//!
+//! | Symbol     | Square                               |
+//! |------------|--------------------------------------|
+//! | `░`        | Closed                               |
+//! | `F`        | Flagged                              |
+//! | ` ` or `0` | Opened, no mines around              |
+//! | `1` - `9`  | Opened, with that many mines around  |
+//! | `⑩` and on | Opened, with 10 or more mines around |
+//! | `*`        | Opened mine                          |
+//!
+//! Numbers above 9 only show up with other topologies or rules. A number `n` is written as the
+//! character `n` places after U+245F, which is `⑩` to `⑳` for 10 to 20 and goes on through the
+//! next characters for bigger numbers, so every number is still a single character.
+//!
+//! Closed squares hide what is under them, so a parsed visible board has no mines there and
+//! takes the flags as its mine count. Use [`Minesweeper::from_visible_str`] when the mine
+//! count is known. Numbers are kept as they are written.
+//!
+//! The full board is what `format!("{:#}", game)` prints. Opened squares look the same, and
+//! closed squares also show if they hold a mine:
+//!
+//! | Symbol | Square              |
+//! |--------|---------------------|
+//! | `.`    | Closed, safe        |
+//! | `x`    | Closed mine         |
+//! | `f`    | Flagged, safe       |
+//! | `F`    | Flagged mine        |
+//!
+//! Text without any `░` is read as a full board, and every opened number is checked against
+//! the mines around it. Boards with another topology than the classic one are read with
+//! [`Minesweeper::parse_with_topology`]. Hex boards are written the same way, one row per line,
+//! with the half square shift of the odd rows left out.
+//!
+//! Mine layouts shared by the Minesweeper community can be read and written as well, both as
+//! plain text with `*` for mines and `.` for safe squares, and in the binary MBF format.
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    game::{Minesweeper, MinesweeperError, Square},
+    topology::Topology,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextError {
+    Empty,
+    /// This row is not as long as the first one
+    UnevenRow(usize),
+    UnknownSymbol {
+        row: usize,
+        col: usize,
+        symbol: char,
+    },
+    /// An opened number that does not match the mines around it
+    WrongNumber {
+        row: usize,
+        col: usize,
+        shown: u32,
+        mines: u32,
+    },
+    /// Visible `░` squares mixed with the closed squares of a full board
+    MixedFormats,
+    /// There has to be at least one square without a mine
+    TooManyMines,
+    /// The mine count is below the opened mines shown on the board
+    TooFewMines,
+    /// The board is too small for its topology to wrap around
+    TooSmallToWrap,
+    /// An MBF file that is cut short, or has a mine outside the board
+    InvalidMbf,
+}
+
+impl Display for TextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextError::Empty => write!(f, "The board is empty"),
+            TextError::UnevenRow(row) => {
+                write!(f, "Row {row} is not as long as the first row")
+            }
+            TextError::UnknownSymbol { row, col, symbol } => {
+                write!(f, "Unknown symbol {symbol:?} at ({row}, {col})")
+            }
+            TextError::WrongNumber {
+                row,
+                col,
+                shown,
+                mines,
+            } => write!(
+                f,
+                "The number at ({row}, {col}) is {shown}, but there are {mines} mines around it"
+            ),
+            TextError::MixedFormats => write!(
+                f,
+                "Closed squares of a visible board are mixed with those of a full board"
+            ),
+            TextError::TooManyMines => write!(f, "Every square is a mine"),
+            TextError::TooFewMines => {
+                write!(f, "The board shows more opened mines than its mine count")
+            }
+            TextError::TooSmallToWrap => write!(f, "The board is too small to wrap around"),
+            TextError::InvalidMbf => write!(f, "Not a valid MBF file"),
+        }
+    }
+}
+
+impl std::error::Error for TextError {}
+
+/// Written before the numbers above 9, `⑩` is 10 places after it
+const NUMBER_BASE: u32 = 0x245F;
+
+/// The character of an opened number, see the module docs
+pub(crate) fn number_symbol(n: u32) -> char {
+    match n {
+        0..=9 => char::from_digit(n, 10).expect("Single digits are digits"),
+        _ => char::from_u32(NUMBER_BASE + n).expect("Numbers are at most 256"),
+    }
+}
+
+/// What a single character says about a square
+pub(crate) struct Symbol {
+    pub(crate) square: Option<Square>,
+    pub(crate) is_mine: bool,
+    pub(crate) opened: bool,
+    pub(crate) marked: bool,
+}
+
+pub(crate) fn parse_symbol(symbol: char) -> Option<Symbol> {
+    let (square, is_mine, opened, marked) = match symbol {
+        '░' => (None, false, false, false),
+        ' ' | '0' => (Some(Square::Empty), false, true, false),
+        '1'..='9' => (
+            Some(Square::Nearby(symbol.to_digit(10)?)),
+            false,
+            true,
+            false,
+        ),
+        '⑩'..='\u{255f}' => (
+            Some(Square::Nearby(symbol as u32 - NUMBER_BASE)),
+            false,
+            true,
+            false,
+        ),
+        '*' => (Some(Square::Mine), true, true, false),
+        '.' => (None, false, false, false),
+        'x' => (None, true, false, false),
+        'f' => (None, false, false, true),
+        'F' => (None, true, false, true),
+        _ => return None,
+    };
+
+    Some(Symbol {
+        square,
+        is_mine,
+        opened,
+        marked,
+    })
+}
+
+pub(crate) fn split_rows(s: &str) -> Result<Vec<Vec<char>>, TextError> {
+    let rows = s
+        .lines()
+        .map(|line| line.trim_end_matches('\r').chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let cols = rows.first().map_or(0, Vec::len);
+    if cols == 0 {
+        return Err(TextError::Empty);
+    }
+    if let Some(row) = rows.iter().position(|row| row.len() != cols) {
+        return Err(TextError::UnevenRow(row));
+    }
+
+    Ok(rows)
+}
+
+/// Parses the visible or full board
+impl FromStr for Minesweeper {
+    type Err = TextError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Minesweeper::parse_with_topology(s, Topology::default())
+    }
+}
+
+impl Minesweeper {
+    /// Parses the visible or full board, counting neighbours with `topology`
+    pub fn parse_with_topology(s: &str, topology: Topology) -> Result<Self, TextError> {
+        let rows = split_rows(s)?;
+        if !topology.fits(rows.len(), rows[0].len()) {
+            return Err(TextError::TooSmallToWrap);
+        }
+
+        let is_visible = rows.iter().flatten().any(|&symbol| symbol == '░');
+        if !is_visible {
+            return parse_full(&rows, topology);
+        }
+
+        let flags = rows
+            .iter()
+            .flatten()
+            .filter(|&&symbol| symbol == 'F')
+            .count();
+        let opened_mines = rows
+            .iter()
+            .flatten()
+            .filter(|&&symbol| symbol == '*')
+            .count();
+        parse_visible(&rows, flags + opened_mines, topology)
+    }
+
+    /// Parses a visible board, where `mines` is the total amount of mines including the
+    /// ones under closed squares
+    pub fn from_visible_str(s: &str, mines: usize) -> Result<Self, TextError> {
+        parse_visible(&split_rows(s)?, mines, Topology::default())
+    }
+
+    /// The mine layout as plain text, `*` for mines and `.` for safe squares
+    pub fn to_layout_string(&self) -> String {
+        let mut s = String::new();
+        for row in 0..self.rows() {
+            for col in 0..self.cols() {
+                s.push(if self.is_mine(row, col) { '*' } else { '.' });
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    /// Reads a plain text mine layout, `*` for mines and `.` for safe squares
+    pub fn from_layout_str(s: &str) -> Result<Self, TextError> {
+        let rows = split_rows(s)?;
+
+        let mut layout = Vec::new();
+        for (row, symbols) in rows.iter().enumerate() {
+            let mut layout_row = Vec::new();
+            for (col, &symbol) in symbols.iter().enumerate() {
+                layout_row.push(match symbol {
+                    '*' => true,
+                    '.' => false,
+                    _ => return Err(TextError::UnknownSymbol { row, col, symbol }),
+                });
+            }
+            layout.push(layout_row);
+        }
+
+        from_layout(&layout)
+    }
+
+    /// The mine layout in the MBF format: width, height, mine count as big endian `u16`,
+    /// followed by `x, y` for every mine. Boards larger than 255 squares in either direction
+    /// can not be written.
+    pub fn to_mbf(&self) -> Option<Vec<u8>> {
+        let width = u8::try_from(self.cols()).ok()?;
+        let height = u8::try_from(self.rows()).ok()?;
+        let positions = (0..self.rows())
+            .flat_map(|row| (0..self.cols()).map(move |col| (row, col)))
+            .filter(|&(row, col)| self.is_mine(row, col))
+            .flat_map(|(row, col)| [col as u8, row as u8])
+            .collect::<Vec<_>>();
+        let mines = u16::try_from(positions.len() / 2).ok()?;
+
+        let mut mbf = vec![width, height];
+        mbf.extend(mines.to_be_bytes());
+        mbf.extend(positions);
+
+        Some(mbf)
+    }
+
+    pub fn from_mbf(mbf: &[u8]) -> Result<Self, TextError> {
+        let [width, height, mines_high, mines_low, positions @ ..] = mbf else {
+            return Err(TextError::InvalidMbf);
+        };
+        let (cols, rows) = (*width as usize, *height as usize);
+        let mines = u16::from_be_bytes([*mines_high, *mines_low]) as usize;
+
+        if cols == 0 || rows == 0 {
+            return Err(TextError::Empty);
+        }
+        if positions.len() < mines * 2 {
+            return Err(TextError::InvalidMbf);
+        }
+
+        let mut layout = vec![vec![false; cols]; rows];
+        for position in positions.chunks_exact(2).take(mines) {
+            let (col, row) = (position[0] as usize, position[1] as usize);
+            if col >= cols || row >= rows || layout[row][col] {
+                return Err(TextError::InvalidMbf);
+            }
+            layout[row][col] = true;
+        }
+
+        from_layout(&layout)
+    }
+}
+
+fn from_layout(layout: &[Vec<bool>]) -> Result<Minesweeper, TextError> {
+    Minesweeper::from_mine_layout(layout).map_err(|err| match err {
+        MinesweeperError::EmptyBoard => TextError::Empty,
+        MinesweeperError::UnevenLayout(row) => TextError::UnevenRow(row),
+        _ => TextError::TooManyMines,
+    })
+}
+
+fn parse_full(rows: &[Vec<char>], topology: Topology) -> Result<Minesweeper, TextError> {
+    let mut layout = Vec::new();
+    let mut opened = Vec::new();
+    let mut marked = Vec::new();
+    let mut shown = Vec::new();
+
+    for (row, symbols) in rows.iter().enumerate() {
+        let mut layout_row = Vec::new();
+        let mut opened_row = Vec::new();
+        let mut marked_row = Vec::new();
+
+        for (col, &symbol) in symbols.iter().enumerate() {
+            let parsed =
+                parse_symbol(symbol).ok_or(TextError::UnknownSymbol { row, col, symbol })?;
+
+            layout_row.push(parsed.is_mine);
+            opened_row.push(parsed.opened);
+            marked_row.push(parsed.marked);
+            if let Some(square) = parsed.square {
+                shown.push((row, col, square));
+            }
+        }
+
+        layout.push(layout_row);
+        opened.push(opened_row);
+        marked.push(marked_row);
+    }
+
+    let minesweeper = from_layout(&layout)?
+        .with_topology(topology)
+        .map_err(|_| TextError::TooSmallToWrap)?;
+
+    for (row, col, square) in shown {
+        let shown = match square {
+            Square::Empty => 0,
+            Square::Nearby(n) => n,
+            Square::Mine => continue,
+        };
+        let mines = match minesweeper.shown(row, col) {
+            Square::Empty => 0,
+            Square::Nearby(n) => n,
+            Square::Mine => continue,
+        };
+
+        if shown != mines {
+            return Err(TextError::WrongNumber {
+                row,
+                col,
+                shown,
+                mines,
+            });
+        }
+    }
+
+    let grid = (0..minesweeper.rows())
+        .map(|row| {
+            (0..minesweeper.cols())
+                .map(|col| minesweeper.shown(row, col))
+                .collect()
+        })
+        .collect();
+    let mines = minesweeper.mines();
+    let topology = minesweeper.topology().clone();
+    Ok(Minesweeper::from_parts(
+        grid, opened, marked, mines, topology, false,
+    ))
+}
+
+pub(crate) fn parse_visible(
+    rows: &[Vec<char>],
+    mines: usize,
+    topology: Topology,
+) -> Result<Minesweeper, TextError> {
+    let mut grid = Vec::new();
+    let mut opened = Vec::new();
+    let mut marked = Vec::new();
+
+    for (row, symbols) in rows.iter().enumerate() {
+        let mut grid_row = Vec::new();
+        let mut opened_row = Vec::new();
+        let mut marked_row = Vec::new();
+
+        for (col, &symbol) in symbols.iter().enumerate() {
+            if matches!(symbol, '.' | 'x' | 'f') {
+                return Err(TextError::MixedFormats);
+            }
+            let parsed =
+                parse_symbol(symbol).ok_or(TextError::UnknownSymbol { row, col, symbol })?;
+
+            grid_row.push(parsed.square.unwrap_or(Square::Empty));
+            opened_row.push(parsed.opened);
+            marked_row.push(parsed.marked);
+        }
+
+        grid.push(grid_row);
+        opened.push(opened_row);
+        marked.push(marked_row);
+    }
+
+    let closed = opened.iter().flatten().filter(|&&opened| !opened).count();
+    let opened_mines = grid
+        .iter()
+        .flatten()
+        .filter(|square| matches!(square, Square::Mine))
+        .count();
+    if mines > closed + opened_mines {
+        return Err(TextError::TooManyMines);
+    }
+    if mines < opened_mines {
+        return Err(TextError::TooFewMines);
+    }
+
+    Ok(Minesweeper::from_parts(
+        grid, opened, marked, mines, topology, true,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game::FirstClick, rules::Rule};
+
+    /// Boards in the middle of a game, with flags, on several topologies
+    fn boards() -> Vec<Minesweeper> {
+        let mut boards = Vec::new();
+        for topology in ["square", "cross", "knight+wrap", "hex"] {
+            for seed in 0..10 {
+                let mut game =
+                    Minesweeper::new_deferred_seeded(9, 12, 20, FirstClick::Opening, seed)
+                        .unwrap()
+                        .with_topology(topology.parse().unwrap())
+                        .unwrap();
+                game.click(4, 6).unwrap();
+                for (row, col) in [(0, 0), (8, 11), (2, 9)] {
+                    let _ = game.mark(row, col);
+                }
+                boards.push(game);
+            }
+        }
+        boards
+    }
+
+    #[test]
+    fn full_boards_read_back() {
+        for game in boards() {
+            let text = format!("{game:#}");
+            let read = Minesweeper::parse_with_topology(&text, game.topology().clone()).unwrap();
+            assert_eq!(format!("{read:#}"), text);
+            assert_eq!(read.to_string(), game.to_string());
+            assert_eq!(read.mines(), game.mines());
+        }
+    }
+
+    #[test]
+    fn visible_boards_read_back() {
+        for game in boards() {
+            let text = game.to_string();
+            let read = Minesweeper::from_visible_str(&text, game.mines()).unwrap();
+            assert_eq!(read.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn visible_boards_need_their_opened_mines() {
+        assert!(matches!(
+            Minesweeper::from_visible_str("*░\n░░", 0),
+            Err(TextError::TooFewMines)
+        ));
+        assert!(Minesweeper::from_visible_str("*░\n░░", 1).is_ok());
+    }
+
+    #[test]
+    fn numbers_above_nine_read_back() {
+        let mut layout = vec![vec![true; 3]; 3];
+        layout[1][1] = false;
+        let mut game = Minesweeper::from_mine_layout(&layout)
+            .unwrap()
+            .with_rules(vec![Rule::DoubleMines(8)]);
+        game.click(1, 1).unwrap();
+        assert_eq!(game.square_state(1, 1), Some(Square::Nearby(16)));
+
+        let text = game.to_string();
+        assert_eq!(text.lines().nth(1), Some("░⑯░"));
+        let read = text.parse::<Minesweeper>().unwrap();
+        assert_eq!(read.square_state(1, 1), Some(Square::Nearby(16)));
+        assert_eq!(read.to_string(), text);
+    }
+
+    #[test]
+    fn mine_layouts_read_back() {
+        for game in boards() {
+            let layout = game.to_layout_string();
+            assert_eq!(
+                Minesweeper::from_layout_str(&layout)
+                    .unwrap()
+                    .to_layout_string(),
+                layout
+            );
+
+            let mbf = game.to_mbf().unwrap();
+            assert_eq!(mbf[..4], [12, 9, 0, 20]);
+            assert_eq!(
+                Minesweeper::from_mbf(&mbf).unwrap().to_layout_string(),
+                layout
+            );
+        }
+    }
+}
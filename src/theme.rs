@@ -0,0 +1,294 @@
+//! How the board looks.
+//!
+//! The built in theme is always there, and every directory in `assets/themes` with a
+//! `theme.conf` adds another. The file has `key = value` lines like the config, anything left
+//! out is taken from the built in theme:
+//!
+//! ```text
+//! name = Classic
+//! background = #808080
+//! closed = #c0c0c0
+//! number.1 = #0000ff
+//! bevel = true
+//! ```
+//!
+//! Colours are `#rrggbb`. The colour keys are `background`, `hud`, `closed`, `open`, `mine`
+//! for an opened mine, `hint`, `heat` for the closed squares the AI or the probabilities are
+//! sure of, `probability` for their numbers, `counter`, `counter_text`, `cursor` and
+//! `number.1` to `number.8`. Higher numbers use the colour of 8. `number_scale` is the size of
+//! the numbers compared to a square, `bevel` draws closed squares raised, and `font` is a font
+//! file in the theme's directory.
+//!
+//! A theme's directory can also hold textures, which are drawn instead of the colours:
+//! `closed.png`, `open.png`, `flag.png`, `mine.png`, `mine_hit.png`, `wrong_flag.png` and
+//! `1.png` to `8.png`. See [`TEXTURES`].
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::config::ConfigError;
+
+/// The textures a theme's directory can have, without the `.png`
+pub const TEXTURES: [&str; 14] = [
+    "closed",
+    "open",
+    "flag",
+    "mine",
+    "mine_hit",
+    "wrong_flag",
+    "1",
+    "2",
+    "3",
+    "4",
+    "5",
+    "6",
+    "7",
+    "8",
+];
+
+/// Red, green and blue from 0 to 1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Colour(pub f32, pub f32, pub f32);
+
+impl Colour {
+    /// Mixes in `amount` of `other`, all of it at 1
+    pub fn mix(self, other: Colour, amount: f32) -> Colour {
+        let amount = amount.clamp(0., 1.);
+        Colour(
+            self.0 + (other.0 - self.0) * amount,
+            self.1 + (other.1 - self.1) * amount,
+            self.2 + (other.2 - self.2) * amount,
+        )
+    }
+}
+
+impl Display for Colour {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let byte = |channel: f32| (channel * 255.).round() as u8;
+        write!(
+            f,
+            "#{:02x}{:02x}{:02x}",
+            byte(self.0),
+            byte(self.1),
+            byte(self.2)
+        )
+    }
+}
+
+impl FromStr for Colour {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid colour {s:?}, expected #rrggbb");
+        let hex = s
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6 && hex.is_ascii())
+            .ok_or_else(invalid)?;
+        let channel = |i: usize| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map(|byte| byte as f32 / 255.)
+                .map_err(|_| invalid())
+        };
+
+        Ok(Colour(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    /// Where the textures and the font are, None for the built in theme
+    pub dir: Option<PathBuf>,
+    pub background: Colour,
+    /// The bar above the board
+    pub hud: Colour,
+    pub closed: Colour,
+    pub open: Colour,
+    pub mine: Colour,
+    pub hint: Colour,
+    pub heat: Colour,
+    pub probability: Colour,
+    pub counter: Colour,
+    pub counter_text: Colour,
+    pub cursor: Colour,
+    /// The colours of 1 to 8
+    pub numbers: [Colour; 8],
+    /// Size of the numbers compared to a square
+    pub number_scale: f32,
+    /// Closed squares are drawn raised, like in the classic game
+    pub bevel: bool,
+    /// A font file in the theme's directory
+    pub font: Option<String>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let gray = Colour(0.5, 0.5, 0.5);
+        Theme {
+            name: "Default".to_string(),
+            dir: None,
+            background: gray,
+            hud: gray,
+            closed: Colour(1., 1., 1.),
+            open: Colour(0.3, 0.3, 0.3),
+            mine: Colour(0.8, 0.3, 0.3),
+            hint: Colour(0.6, 1., 0.6),
+            heat: Colour(1., 0., 0.),
+            probability: Colour(0., 0.5, 0.),
+            counter: Colour(0., 0., 0.),
+            counter_text: Colour(1., 0., 0.),
+            cursor: Colour(1., 1., 0.),
+            numbers: [Colour(1., 1., 1.); 8],
+            number_scale: 0.6,
+            bevel: false,
+            font: None,
+        }
+    }
+}
+
+impl Theme {
+    /// The colour of a number, numbers above 8 share the colour of 8
+    pub fn number(&self, n: u32) -> Colour {
+        self.numbers[(n.clamp(1, 8) - 1) as usize]
+    }
+
+    /// Reads `theme.conf` in `dir`, a theme without a name is named after its directory
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let dir = dir.as_ref();
+        let mut theme = std::fs::read_to_string(dir.join("theme.conf"))?.parse::<Theme>()?;
+        if theme.name.is_empty() {
+            theme.name = dir.file_name().map_or_else(
+                || dir.display().to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            );
+        }
+        theme.dir = Some(dir.to_path_buf());
+        Ok(theme)
+    }
+
+    /// The path of a file in the theme's directory, if the theme has one
+    pub fn file(&self, name: &str) -> Option<PathBuf> {
+        self.dir
+            .as_ref()
+            .map(|dir| dir.join(name))
+            .filter(|path| path.is_file())
+    }
+}
+
+/// Every directory in `dir` with a `theme.conf`, sorted by name
+pub fn find_themes(dir: impl AsRef<Path>) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut themes = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.join("theme.conf").is_file())
+        .collect::<Vec<_>>();
+    themes.sort();
+    themes
+}
+
+impl FromStr for Theme {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut theme = Theme {
+            name: String::new(),
+            ..Theme::default()
+        };
+
+        for (i, line) in s.lines().enumerate() {
+            let malformed = |reason: String| ConfigError::Malformed {
+                line: i + 1,
+                reason,
+            };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| malformed("expected key = value".to_string()))?;
+            let (key, value) = (key.trim(), value.trim());
+            let colour = || value.parse::<Colour>().map_err(malformed);
+
+            match key {
+                "name" => theme.name = value.to_string(),
+                "background" => theme.background = colour()?,
+                "hud" => theme.hud = colour()?,
+                "closed" => theme.closed = colour()?,
+                "open" => theme.open = colour()?,
+                "mine" => theme.mine = colour()?,
+                "hint" => theme.hint = colour()?,
+                "heat" => theme.heat = colour()?,
+                "probability" => theme.probability = colour()?,
+                "counter" => theme.counter = colour()?,
+                "counter_text" => theme.counter_text = colour()?,
+                "cursor" => theme.cursor = colour()?,
+                "number_scale" => {
+                    theme.number_scale = value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|scale| (0.1..=1.).contains(scale))
+                        .ok_or_else(|| malformed(format!("{value:?} is not between 0.1 and 1")))?;
+                }
+                "bevel" => {
+                    theme.bevel = value
+                        .parse()
+                        .map_err(|_| malformed(format!("{value:?} is not true or false")))?;
+                }
+                "font" => theme.font = Some(value.to_string()),
+                key => {
+                    let n = key
+                        .strip_prefix("number.")
+                        .and_then(|n| n.parse::<usize>().ok())
+                        .filter(|n| (1..=8).contains(n))
+                        .ok_or_else(|| malformed(format!("unknown setting {key:?}")))?;
+                    theme.numbers[n - 1] = colour()?;
+                }
+            }
+        }
+
+        Ok(theme)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shipped_themes() -> Vec<Theme> {
+        find_themes(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/themes"))
+            .into_iter()
+            .map(|dir| Theme::load(dir).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn shipped_themes_have_their_files() {
+        let themes = shipped_themes();
+        assert!(themes.iter().any(|theme| TEXTURES
+            .iter()
+            .any(|name| theme.file(&format!("{name}.png")).is_some())));
+        assert!(themes.iter().any(|theme| theme.font.is_some()));
+
+        for theme in themes {
+            if let Some(font) = &theme.font {
+                assert!(theme.file(font).is_some(), "{} has no {font}", theme.name);
+            }
+        }
+    }
+
+    #[test]
+    fn colour_blind_numbers_do_not_look_like_mines() {
+        let theme = shipped_themes()
+            .into_iter()
+            .find(|theme| theme.name == "Colour blind")
+            .unwrap();
+        assert!(!theme.numbers.contains(&theme.mine));
+    }
+}
@@ -0,0 +1,215 @@
+//! Which squares count as neighbours of each other.
+//!
+//! Numbers, flood fill, chording, the solver and the training labels all go through
+//! [`Topology::neighbours`], so they work the same for every topology.
+
+use std::{fmt::Display, str::FromStr};
+
+/// The squares around `(0, 0)` that are neighbours, as `(row, col)` offsets
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Kernel {
+    /// The 8 surrounding squares
+    Square,
+    /// Only the 4 orthogonal squares, a "+"
+    Cross,
+    /// The 8 squares a chess knight can jump to
+    Knight,
+    /// The 6 sides of a hexagon, with odd rows shifted half a square to the right
+    Hex,
+    Custom(Vec<(i32, i32)>),
+}
+
+const SQUARE: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+const CROSS: [(i32, i32); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+
+const KNIGHT: [(i32, i32); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+
+const HEX_EVEN_ROW: [(i32, i32); 6] = [(-1, -1), (-1, 0), (0, -1), (0, 1), (1, -1), (1, 0)];
+
+const HEX_ODD_ROW: [(i32, i32); 6] = [(-1, 0), (-1, 1), (0, -1), (0, 1), (1, 0), (1, 1)];
+
+impl Kernel {
+    /// The offsets from a square on `row`, only hexagons differ between even and odd rows
+    pub fn offsets(&self, row: usize) -> &[(i32, i32)] {
+        match self {
+            Kernel::Square => &SQUARE,
+            Kernel::Cross => &CROSS,
+            Kernel::Knight => &KNIGHT,
+            Kernel::Hex if row.is_multiple_of(2) => &HEX_EVEN_ROW,
+            Kernel::Hex => &HEX_ODD_ROW,
+            Kernel::Custom(offsets) => offsets,
+        }
+    }
+
+    /// True when every square is a neighbour of all its neighbours. Only a custom kernel can
+    /// reach one way without reaching back.
+    pub fn is_symmetric(&self) -> bool {
+        match self {
+            Kernel::Custom(offsets) => offsets
+                .iter()
+                .all(|&(row, col)| offsets.contains(&(-row, -col))),
+            _ => true,
+        }
+    }
+
+    /// How far away the furthest neighbour is in rows and cols
+    fn reach(&self) -> (usize, usize) {
+        self.offsets(0)
+            .iter()
+            .chain(self.offsets(1))
+            .fold((0, 0), |(rows, cols), &(row, col)| {
+                (
+                    rows.max(row.unsigned_abs() as usize),
+                    cols.max(col.unsigned_abs() as usize),
+                )
+            })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Topology {
+    pub kernel: Kernel,
+    /// Edges wrap around to the other side, making the board a torus
+    pub wrap: bool,
+}
+
+impl Default for Topology {
+    fn default() -> Self {
+        Topology {
+            kernel: Kernel::Square,
+            wrap: false,
+        }
+    }
+}
+
+impl Topology {
+    pub fn new(kernel: Kernel, wrap: bool) -> Self {
+        Topology { kernel, wrap }
+    }
+
+    /// The classic neighbourhood on a board where the edges wrap around
+    pub fn torus() -> Self {
+        Topology::new(Kernel::Square, true)
+    }
+
+    /// A wrapping board has to be wider than the kernel reaches in both directions, or squares
+    /// would be neighbours of themselves or be counted twice. Wrapping hexagons also need an
+    /// even amount of rows, so the shifted rows line up across the edge.
+    pub fn fits(&self, rows: usize, cols: usize) -> bool {
+        let (reach_rows, reach_cols) = self.kernel.reach();
+        !self.wrap
+            || rows > reach_rows * 2
+                && cols > reach_cols * 2
+                && (self.kernel != Kernel::Hex || rows.is_multiple_of(2))
+    }
+
+    /// All neighbours of `(row, col)` on a `rows` x `cols` board
+    pub fn neighbours(
+        &self,
+        rows: usize,
+        cols: usize,
+        row: usize,
+        col: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let (rows, cols) = (rows as i32, cols as i32);
+        let wrap = self.wrap;
+
+        self.kernel
+            .offsets(row)
+            .iter()
+            .filter_map(move |&(row_delta, col_delta)| {
+                let (mut r, mut c) = (row as i32 + row_delta, col as i32 + col_delta);
+                if wrap {
+                    r = r.rem_euclid(rows);
+                    c = c.rem_euclid(cols);
+                }
+
+                ((0..rows).contains(&r) && (0..cols).contains(&c))
+                    .then_some((r as usize, c as usize))
+            })
+    }
+}
+
+/// Written as the kernel name, `square`, `cross`, `knight`, `hex` or
+/// `custom(row,col;row,col;...)`, followed by `+wrap` for a torus
+impl Display for Topology {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kernel {
+            Kernel::Square => write!(f, "square")?,
+            Kernel::Cross => write!(f, "cross")?,
+            Kernel::Knight => write!(f, "knight")?,
+            Kernel::Hex => write!(f, "hex")?,
+            Kernel::Custom(offsets) => {
+                let offsets = offsets
+                    .iter()
+                    .map(|(row, col)| format!("{row},{col}"))
+                    .collect::<Vec<_>>();
+                write!(f, "custom({})", offsets.join(";"))?
+            }
+        }
+
+        if self.wrap {
+            write!(f, "+wrap")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Topology {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kernel, wrap) = match s.strip_suffix("+wrap") {
+            Some(kernel) => (kernel, true),
+            None => (s, false),
+        };
+
+        let kernel = match kernel {
+            "square" => Kernel::Square,
+            "cross" => Kernel::Cross,
+            "knight" => Kernel::Knight,
+            "hex" => Kernel::Hex,
+            _ => {
+                let offsets = kernel
+                    .strip_prefix("custom(")
+                    .and_then(|offsets| offsets.strip_suffix(')'))
+                    .ok_or_else(|| format!("Unknown topology {s:?}"))?;
+
+                Kernel::Custom(
+                    offsets
+                        .split(';')
+                        .map(|offset| {
+                            let (row, col) = offset.split_once(',')?;
+                            Some((row.trim().parse().ok()?, col.trim().parse().ok()?))
+                        })
+                        .collect::<Option<Vec<_>>>()
+                        .filter(|offsets| !offsets.contains(&(0, 0)))
+                        .ok_or_else(|| format!("Invalid offsets in {s:?}"))?,
+                )
+            }
+        };
+
+        Ok(Topology { kernel, wrap })
+    }
+}
@@ -1,25 +1,122 @@
 use crate::constants::*;
 
-pub(crate) fn row_col_to_x_y(row: usize, col: usize) -> (f32, f32) {
+// The board is laid out in an `area` of `(width, height)` centred on the origin, the camera
+// then moves and scales it onto the window.
+
+/// Side of a single square when a `rows` x `cols` board fits in the area. Squares stay square,
+/// so a board that is wider than it is tall leaves room above and below it.
+pub(crate) fn square_size(rows: usize, cols: usize, area: (f32, f32)) -> f32 {
+    let (width, height) = area;
+
+    ((width / cols as f32).min(height / rows as f32) - SQUARE_MARGIN).max(1.)
+}
+
+/// Width and height of the board
+fn board_size(rows: usize, cols: usize, area: (f32, f32)) -> (f32, f32) {
+    let step = square_size(rows, cols, area) + SQUARE_MARGIN;
+    (step * cols as f32, step * rows as f32)
+}
+
+pub(crate) fn row_col_to_x_y(
+    row: usize,
+    col: usize,
+    rows: usize,
+    cols: usize,
+    area: (f32, f32),
+) -> (f32, f32) {
+    let step = square_size(rows, cols, area) + SQUARE_MARGIN;
+    let (board_width, board_height) = board_size(rows, cols, area);
     (
-        SQUARE_WIDTH / 2. + (SQUARE_WIDTH + SQUARE_MARGIN) * col as f32 - SCREEN_WIDTH as f32 / 2.
-            + SCREEN_PADDING,
-        SQUARE_HEIGHT / 2. + (SQUARE_HEIGHT + SQUARE_MARGIN) * row as f32
-            - SCREEN_HEIGHT as f32 / 2.
-            + SCREEN_PADDING,
+        -board_width / 2. + step * (col as f32 + 0.5),
+        -board_height / 2. + step * (row as f32 + 0.5),
     )
 }
 
-pub(crate) fn x_y_to_row_col(x: f32, y: f32) -> Option<(usize, usize)> {
-    let col =
-        ((-x - SCREEN_WIDTH as f32 / 2. + SCREEN_PADDING) / -(SQUARE_WIDTH + SQUARE_MARGIN)) as i32;
-
-    let row = ((-y - SCREEN_HEIGHT as f32 / 2. + SCREEN_PADDING) / -(SQUARE_HEIGHT + SQUARE_MARGIN))
-        as i32;
+pub(crate) fn x_y_to_row_col(
+    x: f32,
+    y: f32,
+    rows: usize,
+    cols: usize,
+    area: (f32, f32),
+) -> Option<(usize, usize)> {
+    let step = square_size(rows, cols, area) + SQUARE_MARGIN;
+    let (board_width, board_height) = board_size(rows, cols, area);
+    let col = ((x + board_width / 2.) / step).floor() as i32;
+    let row = ((y + board_height / 2.) / step).floor() as i32;
 
-    if (0..COLS as i32).contains(&col) && (0..ROWS as i32).contains(&row) {
+    if (0..cols as i32).contains(&col) && (0..rows as i32).contains(&row) {
         return Some((row as usize, col as usize));
     }
 
     None
 }
+
+/// Distance from the centre to a corner of a single hexagon when a `rows` x `cols` hex board
+/// fits in the area. Hexagons point up, and odd rows are shifted half a hexagon to the right.
+pub(crate) fn hex_size(rows: usize, cols: usize, area: (f32, f32)) -> f32 {
+    let (width, height) = area;
+    let shift = if rows > 1 { 0.5 } else { 0. };
+
+    (width / (3f32.sqrt() * (cols as f32 + shift))).min(height / (1.5 * rows as f32 + 0.5))
+}
+
+pub(crate) fn hex_row_col_to_x_y(
+    row: usize,
+    col: usize,
+    rows: usize,
+    cols: usize,
+    area: (f32, f32),
+) -> (f32, f32) {
+    let size = hex_size(rows, cols, area);
+    let hex_width = 3f32.sqrt() * size;
+    let shift = if rows > 1 { 0.5 } else { 0. };
+    let board_width = hex_width * (cols as f32 + shift);
+    let board_height = size * (1.5 * rows as f32 + 0.5);
+
+    (
+        -board_width / 2. + hex_width * (col as f32 + 0.5 + (row % 2) as f32 * 0.5),
+        -board_height / 2. + size + size * 1.5 * row as f32,
+    )
+}
+
+/// The corners of a hexagon centred on `(x, y)`, starting at the top and going clockwise
+pub(crate) fn hexagon(x: f32, y: f32, size: f32) -> [(f32, f32); 6] {
+    let (half_width, half_size) = (3f32.sqrt() / 2. * size, size / 2.);
+    [
+        (x, y + size),
+        (x + half_width, y + half_size),
+        (x + half_width, y - half_size),
+        (x, y - size),
+        (x - half_width, y - half_size),
+        (x - half_width, y + half_size),
+    ]
+}
+
+/// The hexagon under `(x, y)`, the gaps between hexagons belong to the closest one
+pub(crate) fn x_y_to_hex_row_col(
+    x: f32,
+    y: f32,
+    rows: usize,
+    cols: usize,
+    area: (f32, f32),
+) -> Option<(usize, usize)> {
+    let size = hex_size(rows, cols, area);
+    let (_, bottom) = hex_row_col_to_x_y(0, 0, rows, cols, area);
+    let row_guess = ((y - bottom) / (size * 1.5)).round() as i32;
+
+    // Only the rows next to the guess can hold the point, and in them only the columns around it
+    (row_guess - 1..=row_guess + 1)
+        .filter(|row| (0..rows as i32).contains(row))
+        .flat_map(|row| {
+            let (left, _) = hex_row_col_to_x_y(row as usize, 0, rows, cols, area);
+            let col_guess = ((x - left) / (3f32.sqrt() * size)).round() as i32;
+            (col_guess - 1..=col_guess + 1).map(move |col| (row, col))
+        })
+        .filter(|(_, col)| (0..cols as i32).contains(col))
+        .map(|(row, col)| (row as usize, col as usize))
+        .find(|&(row, col)| {
+            let (center_x, center_y) = hex_row_col_to_x_y(row, col, rows, cols, area);
+            let (dx, dy) = ((x - center_x).abs(), (y - center_y).abs());
+            dx <= 3f32.sqrt() / 2. * size && dy <= size - dx / 3f32.sqrt()
+        })
+}